@@ -0,0 +1,116 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use mongodb::bson::Document;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{instance::ProfileInstance, ProfileEvent};
+
+/// Profile documents most recently read, dropped when the instance changes them.
+pub struct ProfileCache {
+    entries: Mutex<LruCache<u64, Cached>>,
+    /// Bumped by every invalidation, so reads started before one aren't cached after it.
+    generation: AtomicU64,
+}
+
+struct Cached {
+    profile: Arc<Document>,
+    stored: Instant,
+}
+
+impl Default for ProfileCache {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(LruCache::unbounded()),
+            generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ProfileCache {
+    /// The cached profile, unless it is older than `ttl`.
+    pub(crate) fn get(&self, id: u64, ttl: Duration) -> Option<Arc<Document>> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(&id) {
+            Some(cached) if cached.stored.elapsed() < ttl => Some(cached.profile.clone()),
+            Some(_) => {
+                entries.pop(&id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Caches a profile read at `generation`, evicting the least recently used ones past `size`.
+    pub(crate) fn put(&self, id: u64, profile: Arc<Document>, generation: u64, size: usize) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if self.generation() != generation {
+            return;
+        }
+
+        entries.put(
+            id,
+            Cached {
+                profile,
+                stored: Instant::now(),
+            },
+        );
+
+        while entries.len() > size {
+            entries.pop_lru();
+        }
+    }
+
+    pub fn invalidate(&self, id: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.pop(&id);
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ProfileInstance {
+    /// Spawns the task dropping cached profiles on profile events, so changes made by other
+    /// instances or tools are seen before the TTL runs out.
+    pub fn spawn_cache_invalidation(&self) -> tokio::task::JoinHandle<()> {
+        let instance = self.clone();
+        let mut events = self.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(ProfileEvent::Changed { id } | ProfileEvent::Deleted { id }) => {
+                        instance.cache.invalidate(id)
+                    }
+                    Ok(ProfileEvent::Reset) | Err(RecvError::Lagged(_)) => instance.cache.clear(),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
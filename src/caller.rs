@@ -0,0 +1,111 @@
+#[cfg(feature = "tls")]
+use axum::extract::ConnectInfo;
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    Json,
+};
+use serde_json::{json, Value};
+
+use crate::instance::ProfileInstance;
+#[cfg(feature = "tls")]
+use crate::TlsPeer;
+
+/// Who a request is made by, used to authorize access to service namespaces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Caller {
+    /// In-process callers, and HTTP callers without a token unless `auth.trust-anonymous` is off.
+    /// Has access to every namespace.
+    Trusted,
+    /// A service identified by its token, owning the namespace of the same name.
+    Service(String),
+}
+
+impl Caller {
+    pub fn owns(&self, service: &str) -> bool {
+        match self {
+            Caller::Trusted => true,
+            Caller::Service(name) => name == service,
+        }
+    }
+
+    /// Whether the caller may act on whole profiles, as trusted callers and `auth.admins` can.
+    pub fn is_admin(&self, instance: &ProfileInstance) -> bool {
+        match self {
+            Caller::Trusted => true,
+            Caller::Service(name) => instance.live().config.auth.admins.contains(name),
+        }
+    }
+
+    /// Whether the caller may remove and restore whole profiles, anyone unless `auth.admins` is
+    /// set.
+    pub fn may_remove_profiles(&self, instance: &ProfileInstance) -> bool {
+        instance.live().config.auth.admins.is_empty() || self.is_admin(instance)
+    }
+}
+
+impl FromRequestParts<ProfileInstance> for Caller {
+    type Rejection = (StatusCode, Json<Value>);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        instance: &ProfileInstance,
+    ) -> Result<Self, Self::Rejection> {
+        let reject = |reason: &str| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({ "type": "error", "reason": reason })),
+            )
+        };
+
+        // A mapped client certificate identifies the caller on its own. When certificates are
+        // required, one that isn't mapped to a service mustn't fall back to tokens.
+        #[cfg(feature = "tls")]
+        if let Some(ConnectInfo(peer)) = parts.extensions.get::<ConnectInfo<TlsPeer>>() {
+            if let Some(identity) = &peer.identity {
+                return Ok(Caller::Service(identity.clone()));
+            }
+
+            let live = instance.live();
+
+            if live
+                .config
+                .server
+                .tls
+                .as_ref()
+                .is_some_and(|tls| tls.client_required)
+            {
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    Json(json!({
+                        "type": "error",
+                        "reason": "client certificate not mapped to a service",
+                    })),
+                ));
+            }
+        }
+
+        let Some(header) = parts.headers.get(AUTHORIZATION) else {
+            return if instance.live().config.auth.trust_anonymous {
+                Ok(Caller::Trusted)
+            } else {
+                Err(reject("missing token"))
+            };
+        };
+
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or_else(|| reject("malformed authorization header"))?;
+
+        instance
+            .live()
+            .config
+            .auth
+            .tokens
+            .get(token)
+            .map(|service| Caller::Service(service.clone()))
+            .ok_or_else(|| reject("unknown token"))
+    }
+}
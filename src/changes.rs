@@ -0,0 +1,176 @@
+use std::{env, time::Duration};
+
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, Bson, Document},
+    change_stream::event::{ChangeStreamEvent, OperationType, ResumeToken},
+    error::{CommandError, ErrorKind},
+};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::{instance::ProfileInstance, Profile};
+
+/// Events buffered for each subscriber before it starts missing them.
+pub(crate) const EVENT_BACKLOG: usize = 1024;
+
+/// Time to wait before reopening a failed change stream.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A change to the profile collection, made by any instance or tool.
+///
+/// Events may be delivered again after a restart, up to the last saved resume token.
+#[derive(Clone, Debug)]
+pub enum ProfileEvent {
+    /// Profile `id` was created or changed, including being removed or restored.
+    Changed { id: u64 },
+    /// Profile `id` was permanently deleted.
+    Deleted { id: u64 },
+    /// Changes may have been missed, anything derived from profiles should be reread.
+    Reset,
+}
+
+impl ProfileEvent {
+    fn from_change(change: &ChangeStreamEvent<Document>) -> Self {
+        let id = change
+            .document_key
+            .as_ref()
+            .and_then(|key| key.get("_id"))
+            .and_then(Bson::as_i64)
+            .map(|id| id as u64);
+
+        match (&change.operation_type, id) {
+            (OperationType::Insert | OperationType::Update | OperationType::Replace, Some(id)) => {
+                Self::Changed { id }
+            }
+            (OperationType::Delete, Some(id)) => Self::Deleted { id },
+            _ => Self::Reset,
+        }
+    }
+}
+
+impl ProfileInstance {
+    /// Receives every profile event from the moment of subscribing, a lagging receiver should be
+    /// treated as [`ProfileEvent::Reset`].
+    pub fn subscribe(&self) -> broadcast::Receiver<ProfileEvent> {
+        self.changes.subscribe()
+    }
+
+    /// Spawns the task turning a change stream on the profile collection into profile events,
+    /// resuming where the previous run left off.
+    pub fn spawn_watch(&self) -> tokio::task::JoinHandle<()> {
+        let instance = self.clone();
+
+        tokio::spawn(async move {
+            let config = instance.live().config.watch.clone();
+            let name = config
+                .name
+                .or_else(|| env::var("HOSTNAME").ok())
+                .unwrap_or_else(|| "default".to_string());
+            let checkpoint = Duration::from_secs(config.checkpoint_interval.max(1));
+
+            let mut token = match instance.load_resume_token(&name).await {
+                Ok(token) => token,
+                Err(e) => {
+                    error!(error = %e, "failed to load resume token");
+                    let _ = instance.changes.send(ProfileEvent::Reset);
+                    None
+                }
+            };
+
+            loop {
+                match instance.watch_profiles(&name, &mut token, checkpoint).await {
+                    // Closed after the collection was dropped or renamed.
+                    Ok(()) => {}
+                    Err(e) if Self::history_lost(&e) => {
+                        warn!(error = %e, "resume token expired, changes may have been missed");
+                        token = None;
+                        let _ = instance.changes.send(ProfileEvent::Reset);
+                        continue;
+                    }
+                    Err(e) => error!(error = %e, "profile change stream failed"),
+                }
+
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        })
+    }
+
+    async fn watch_profiles(
+        &self,
+        name: &str,
+        token: &mut Option<ResumeToken>,
+        checkpoint: Duration,
+    ) -> mongodb::error::Result<()> {
+        let mut watch = self.profiles_doc.watch();
+
+        if let Some(token) = token.clone() {
+            watch = watch.start_after(token);
+        }
+
+        let mut stream = watch.await?;
+        info!(resumed = token.is_some(), "watching profile changes");
+
+        let mut interval = tokio::time::interval(checkpoint);
+        let mut saved = token.clone();
+
+        loop {
+            tokio::select! {
+                change = stream.try_next() => {
+                    let Some(change) = change? else {
+                        break;
+                    };
+
+                    let _ = self.changes.send(ProfileEvent::from_change(&change));
+                    *token = stream.resume_token();
+                }
+                _ = interval.tick() => {
+                    if *token != saved {
+                        self.save_resume_token(name, token.as_ref()).await?;
+                        saved = token.clone();
+                    }
+                }
+            }
+        }
+
+        self.save_resume_token(name, token.as_ref()).await
+    }
+
+    async fn load_resume_token(&self, name: &str) -> mongodb::error::Result<Option<ResumeToken>> {
+        Ok(self
+            .resume_tokens
+            .find_one(doc! { "_id": name })
+            .await?
+            .and_then(|saved| saved.get("token").cloned())
+            .and_then(|token| bson::from_bson(token).ok()))
+    }
+
+    async fn save_resume_token(
+        &self,
+        name: &str,
+        token: Option<&ResumeToken>,
+    ) -> mongodb::error::Result<()> {
+        let token = bson::to_bson(&token)
+            .map_err(|e| mongodb::error::Error::custom(format!("bad resume token: {e}")))?;
+
+        self.resume_tokens
+            .update_one(
+                doc! { "_id": name },
+                doc! { "$set": { "token": token, "saved": Bson::Int64(Profile::now() as i64) } },
+            )
+            .upsert(true)
+            .await
+            .map(|_| ())
+    }
+
+    /// Whether the oplog no longer holds the changes after the resume token.
+    fn history_lost(e: &mongodb::error::Error) -> bool {
+        matches!(
+            e.kind.as_ref(),
+            ErrorKind::Command(CommandError {
+                code: 280 | 286,
+                ..
+            })
+        )
+    }
+}
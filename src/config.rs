@@ -1,19 +1,28 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fmt,
     fs::{self, OpenOptions},
     io::Write,
-    path::Path,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 use mongodb::{
-    bson::Document,
-    options::{AuthMechanism, ClientOptions, Credential},
-    Client, Collection,
+    bson::doc,
+    event::{command::CommandEvent, EventHandler},
+    options::{
+        Acknowledgment, AuthMechanism, ClientOptions, Credential, ReadPreference,
+        SelectionCriteria, Tls, TlsOptions, WriteConcern,
+    },
+    Client, Database,
 };
 use serde::{Deserialize, Serialize};
 use serde_default::DefaultFromSerde;
 use serde_inline_default::serde_inline_default;
+use serde_json::{json, Map, Value};
 
-use crate::Profile;
+use crate::{FieldError, StartupError};
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(tag = "type")]
@@ -30,7 +39,7 @@ impl ConnectionType {
     pub fn services() -> Self {
         #[cfg(feature = "services-request")]
         return Self::Http {
-            address: "localhost:8080".to_string(),
+            address: "http://localhost:8080".to_string(),
         };
         #[cfg(all(feature = "services-core", not(feature = "services-request")))]
         return ConnectionType::Native {
@@ -49,6 +58,259 @@ pub struct MasterConfig {
     pub services_connection: ConnectionType,
     #[serde(default)]
     pub mongodb: MongoConfig,
+    #[serde(default)]
+    #[serde(rename = "auto-create")]
+    pub auto_create: AutoCreateConfig,
+    #[serde(default)]
+    pub removal: RemovalConfig,
+    #[serde(default)]
+    pub template: TemplateConfig,
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Refuse to start without a config file or with default credentials.
+    #[serde_inline_default(false)]
+    pub strict: bool,
+    #[serde(default)]
+    pub reload: ReloadConfig,
+    #[serde(default)]
+    pub log: LogConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub watch: WatchConfig,
+}
+
+/// Load shedding for API routes, probes and `/metrics` are never limited.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LimitsConfig {
+    /// Requests handled at once before further ones are answered with 503.
+    #[serde(default, rename = "in-flight")]
+    pub in_flight: Option<usize>,
+    /// Rate limit of each caller without an entry in `callers`.
+    #[serde(default)]
+    pub default: Option<RateLimit>,
+    /// Rate limits by caller identity, `trusted` for callers without a token.
+    #[serde(default)]
+    pub callers: BTreeMap<String, RateLimit>,
+    /// Rate limits applied to each caller separately by route, such as `/set`.
+    #[serde(default)]
+    pub routes: BTreeMap<String, RateLimit>,
+}
+
+/// Token bucket refilled at `rate` tokens per second, holding up to `burst` tokens.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RateLimit {
+    pub rate: f64,
+    pub burst: u32,
+}
+
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct ServerConfig {
+    /// `ip:port` (`[::]:8080` for IPv6) or `unix:/path/to.sock`, `0.0.0.0:{port}` if unset.
+    #[serde(default)]
+    pub bind: Option<String>,
+    /// Path the API is served under, empty to serve it at the root.
+    #[serde_inline_default("/api/profile/v1".to_string())]
+    #[serde(rename = "base-path")]
+    pub base_path: String,
+    /// Seconds in-flight requests get to finish after SIGTERM or SIGINT.
+    #[serde_inline_default(30)]
+    #[serde(rename = "drain-timeout")]
+    pub drain_timeout: u64,
+    /// Serve HTTPS instead of HTTP when set.
+    #[cfg(feature = "tls")]
+    #[serde(default)]
+    pub tls: Option<ServerTlsConfig>,
+}
+
+#[cfg(feature = "tls")]
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ServerTlsConfig {
+    /// PEM certificate chain, leaf first.
+    pub cert: PathBuf,
+    /// PEM private key of the leaf certificate.
+    pub key: PathBuf,
+    /// PEM CA certificates client certificates are verified against, enabling mutual TLS.
+    #[serde(default, rename = "client-ca")]
+    pub client_ca: Option<PathBuf>,
+    /// Reject clients without a certificate instead of falling back to tokens.
+    #[serde_inline_default(false)]
+    #[serde(rename = "client-required")]
+    pub client_required: bool,
+    /// Client certificate subjects with every attribute, such as `C=US,O=Example,CN=orders` in
+    /// any order, or common names mapped to the service the client is authorized as.
+    #[serde(default)]
+    pub identities: BTreeMap<String, String>,
+    /// Seconds between checks of the certificate files for changes.
+    #[serde_inline_default(60)]
+    #[serde(rename = "reload-interval")]
+    pub reload_interval: u64,
+}
+
+/// Where the server listens.
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindAddress::Tcp(address) => write!(f, "{address}"),
+            #[cfg(unix)]
+            BindAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One JSON object per line, including the fields of enclosing spans.
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "text")]
+    Text,
+}
+
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct LogConfig {
+    #[serde_inline_default(LogFormat::Json)]
+    pub format: LogFormat,
+}
+
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct ReloadConfig {
+    /// Seconds between checks of the config file for changes.
+    #[serde_inline_default(5)]
+    pub interval: u64,
+}
+
+/// In-process cache of profiles read by `show`, `show-service` and `show-overlay`.
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct CacheConfig {
+    /// Profiles kept, 0 disables the cache.
+    #[serde_inline_default(0)]
+    pub size: usize,
+    /// Seconds a cached profile is served before it is read again.
+    #[serde_inline_default(30)]
+    pub ttl: u64,
+    /// Seconds a service found in atom-services is assumed to exist, 0 checks on every use.
+    #[serde_inline_default(60)]
+    #[serde(rename = "services-ttl")]
+    pub services_ttl: u64,
+    /// Seconds a namespace schema is used before it is read again, picking up schemas set
+    /// through other instances.
+    #[serde_inline_default(30)]
+    #[serde(rename = "schema-ttl")]
+    pub schema_ttl: u64,
+}
+
+/// Change stream on the profile collection, delivering changes made by other instances and
+/// tools as profile events.
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct WatchConfig {
+    /// Requires MongoDB to run as a replica set.
+    #[serde_inline_default(false)]
+    pub enabled: bool,
+    /// Key the resume token is saved under, unique per instance, the host name by default.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Seconds between saves of the resume token.
+    #[serde_inline_default(1)]
+    #[serde(rename = "checkpoint-interval")]
+    pub checkpoint_interval: u64,
+}
+
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct AuthConfig {
+    /// Bearer tokens mapped to the identity of the service presenting them.
+    #[serde(default)]
+    pub tokens: BTreeMap<String, String>,
+    /// Treat requests without a token as trusted, with access to every namespace, as before
+    /// tokens existed. Turn off once every client sends a token.
+    #[serde_inline_default(true)]
+    #[serde(rename = "trust-anonymous")]
+    pub trust_anonymous: bool,
+    /// Services allowed to remove and restore whole profiles, every caller if empty.
+    #[serde(default)]
+    pub admins: BTreeSet<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Writes violating the schema registry are rejected.
+    #[serde(rename = "strict")]
+    Strict,
+    /// Writes violating the schema registry go through with warnings in the response.
+    #[serde(rename = "warn")]
+    Warn,
+}
+
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct ValidationConfig {
+    #[serde_inline_default(ValidationMode::Strict)]
+    pub mode: ValidationMode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateMode {
+    /// Template values are written into profiles when they are created.
+    #[serde(rename = "materialize")]
+    Materialize,
+    /// Template values are filled in at read time and never stored.
+    #[serde(rename = "virtual")]
+    Virtual,
+}
+
+/// Values every new profile starts with.
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct TemplateConfig {
+    /// Bump after changing the template so `migrate-template` rolls it out to existing profiles.
+    #[serde_inline_default(0)]
+    pub version: u64,
+    #[serde_inline_default(TemplateMode::Materialize)]
+    pub mode: TemplateMode,
+    #[serde(default)]
+    pub bucket: BTreeMap<String, String>,
+    #[serde(default)]
+    pub services: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct RemovalConfig {
+    /// Seconds a removed profile can still be restored before it is purged.
+    #[serde_inline_default(30 * 24 * 60 * 60)]
+    pub retention: u64,
+    /// Seconds between runs of the purge task.
+    #[serde_inline_default(60 * 60)]
+    #[serde(rename = "purge-interval")]
+    pub purge_interval: u64,
+}
+
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct AutoCreateConfig {
+    #[serde_inline_default(true)]
+    pub default: bool,
+    #[serde(default)]
+    pub services: BTreeMap<String, bool>,
 }
 
 #[serde_inline_default]
@@ -66,14 +328,132 @@ pub struct MongoConfig {
     #[serde_inline_default("atomics".to_string())]
     #[serde(rename = "masterDB")]
     pub master_db: String,
+    #[serde_inline_default(MongoAuthMechanism::ScramSha1)]
+    pub mechanism: MongoAuthMechanism,
+    /// Connect over TLS when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<MongoTlsConfig>,
+    #[serde(
+        default,
+        rename = "replica-set",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub replica_set: Option<String>,
+    #[serde(
+        default,
+        rename = "read-preference",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub read_preference: Option<MongoReadPreference>,
+    #[serde(
+        default,
+        rename = "write-concern",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub write_concern: Option<MongoWriteConcern>,
+    #[serde(default)]
+    pub pool: PoolConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MongoAuthMechanism {
+    /// Connect without credentials, for local development.
+    #[serde(rename = "none")]
+    None,
+    #[serde(rename = "SCRAM-SHA-1")]
+    ScramSha1,
+    #[serde(rename = "SCRAM-SHA-256")]
+    ScramSha256,
+    /// Authenticate with the client certificate from `tls.cert-key-file`, `username` is
+    /// optional and `authDB` is ignored.
+    #[serde(rename = "MONGODB-X509")]
+    X509,
+}
+
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct MongoTlsConfig {
+    /// CA certificates to verify the server with, the system roots if unset.
+    #[serde(default, rename = "ca-file", skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<PathBuf>,
+    /// PEM file holding the client certificate followed by its private key.
+    #[serde(
+        default,
+        rename = "cert-key-file",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cert_key_file: Option<PathBuf>,
+    #[serde_inline_default(false)]
+    #[serde(rename = "allow-invalid-certificates")]
+    pub allow_invalid_certificates: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MongoReadPreference {
+    #[serde(rename = "primary")]
+    Primary,
+    #[serde(rename = "primary-preferred")]
+    PrimaryPreferred,
+    #[serde(rename = "secondary")]
+    Secondary,
+    #[serde(rename = "secondary-preferred")]
+    SecondaryPreferred,
+    #[serde(rename = "nearest")]
+    Nearest,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct MongoWriteConcern {
+    /// `majority`, a number of nodes, or a custom tag set name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub w: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal: Option<bool>,
+    /// Milliseconds to wait for the write concern before failing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// Connection pool shared by every collection of the instance.
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct PoolConfig {
+    #[serde_inline_default(10)]
+    #[serde(rename = "max-size")]
+    pub max_size: u32,
+    #[serde_inline_default(0)]
+    #[serde(rename = "min-size")]
+    pub min_size: u32,
+    /// Seconds an unused connection is kept open, forever if unset.
+    #[serde(default, rename = "max-idle", skip_serializing_if = "Option::is_none")]
+    pub max_idle: Option<u64>,
+    /// Milliseconds to wait for a connection to be established.
+    #[serde_inline_default(10_000)]
+    #[serde(rename = "connect-timeout")]
+    pub connect_timeout: u64,
+    /// Milliseconds an operation waits for a suitable server before failing.
+    #[serde_inline_default(5_000)]
+    #[serde(rename = "server-selection-timeout")]
+    pub server_selection_timeout: u64,
+}
+
+impl AutoCreateConfig {
+    /// Whether `set-service` may create a missing profile for `service`.
+    pub fn allows(&self, service: &str) -> bool {
+        self.services.get(service).copied().unwrap_or(self.default)
+    }
 }
 
 impl MasterConfig {
-    fn create(path: &Path) {
-        let ser = serde_json::to_vec_pretty(&Self::default()).unwrap();
+    fn create(path: &Path, format: ConfigFormat) -> Result<(), StartupError> {
+        let ser = format.write(&serde_json::to_value(Self::default()).unwrap());
+        let io_fail = |source| StartupError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
 
-        if !path.parent().unwrap().exists() {
-            fs::create_dir_all(path.parent().unwrap()).unwrap();
+        if let Some(parent) = path.parent().filter(|parent| !parent.exists()) {
+            fs::create_dir_all(parent).map_err(io_fail)?;
         }
 
         OpenOptions::new()
@@ -81,58 +461,680 @@ impl MasterConfig {
             .write(true)
             .truncate(true)
             .open(path)
-            .unwrap()
-            .write_all(&ser)
-            .unwrap();
+            .and_then(|mut file| file.write_all(&ser))
+            .map_err(io_fail)
     }
 
-    pub fn read(path: &Path) -> Self {
+    /// Reads the config file, in a format picked by its extension, with every field
+    /// overridable from `PROFILE_*` environment variables. Fails if the file doesn't exist.
+    pub fn read(path: &Path) -> Result<Self, StartupError> {
         if !path.exists() {
-            Self::create(path);
+            return Err(StartupError::MissingConfig(path.to_path_buf()));
+        }
+
+        let content = fs::read(path).map_err(|source| StartupError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(path, Some(&content))
+    }
+
+    /// Like [`MasterConfig::read`], but creates a missing file with default values first unless
+    /// `strict` is set. Only meant for the first start.
+    pub fn read_or_create(path: &Path) -> Result<Self, StartupError> {
+        if path.exists() {
+            return Self::read(path);
+        }
+
+        let config = Self::parse(path, None)?;
+
+        if config.strict {
+            return Err(StartupError::MissingConfig(path.to_path_buf()));
+        }
+
+        Self::create(path, ConfigFormat::of(path))?;
+        Ok(config)
+    }
+
+    /// Layers the file `content`, if any, and the environment over the default values.
+    fn parse(path: &Path, content: Option<&[u8]>) -> Result<Self, StartupError> {
+        let mut value = serde_json::to_value(Self::default()).unwrap();
+        let parse_fail = |reason: String| StartupError::Parse {
+            path: path.to_path_buf(),
+            reason,
+        };
+
+        if let Some(content) = content {
+            let parsed = ConfigFormat::of(path).parse(content).map_err(parse_fail)?;
+            merge(&mut value, parsed);
+        }
+
+        apply_env(&mut value, &optional_fields(), ENV_PREFIX)?;
+        serde_json::from_value(value).map_err(|e| parse_fail(e.to_string()))
+    }
+
+    /// Copies the fields that can't change while running from `current`, returning `self` along
+    /// with the fields whose new values were discarded.
+    pub(crate) fn keep_restart_fields(self, current: &Self) -> (Self, Vec<&'static str>) {
+        let mut new = serde_json::to_value(self).unwrap();
+        let current = serde_json::to_value(current).unwrap();
+        let mut changed = Vec::new();
+
+        for (field, pointer) in RESTART_FIELDS {
+            if let (Some(new), Some(current)) = (new.pointer_mut(pointer), current.pointer(pointer))
+            {
+                if new != current {
+                    *new = current.clone();
+                    changed.push(field);
+                }
+            }
+        }
+
+        (serde_json::from_value(new).unwrap(), changed)
+    }
+
+    pub fn bind_address(&self) -> Result<BindAddress, String> {
+        let Some(bind) = &self.server.bind else {
+            return Ok(BindAddress::Tcp(SocketAddr::from((
+                [0, 0, 0, 0],
+                self.port,
+            ))));
+        };
+
+        if let Some(path) = bind.strip_prefix("unix:") {
+            #[cfg(unix)]
+            return Ok(BindAddress::Unix(path.into()));
+            #[cfg(not(unix))]
+            return Err(format!("unix sockets are not supported, got {path}"));
+        }
+
+        bind.parse()
+            .map(BindAddress::Tcp)
+            .map_err(|e| format!("{e}, expected ip:port or unix:path"))
+    }
+
+    /// Checks every field, reporting all invalid ones at once.
+    pub fn validate(&self) -> Result<(), StartupError> {
+        let mut errors = Vec::new();
+
+        if self.server.bind.is_none() && self.port == 0 {
+            errors.push(FieldError::new("port", "must not be 0"));
+        }
+
+        if let Err(e) = self.bind_address() {
+            errors.push(FieldError::new("server.bind", e));
+        }
+
+        if !self.server.base_path.is_empty()
+            && (!self.server.base_path.starts_with('/') || self.server.base_path.ends_with('/'))
+        {
+            errors.push(FieldError::new(
+                "server.base-path",
+                "must start and not end with /",
+            ));
+        }
+
+        match &self.services_connection {
+            #[cfg(feature = "services-request")]
+            ConnectionType::Http { address } => match reqwest::Url::parse(address) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                Ok(url) => errors.push(FieldError::new(
+                    "services-connection.address",
+                    format!("unsupported scheme {}", url.scheme()),
+                )),
+                Err(e) => errors.push(FieldError::new("services-connection.address", e)),
+            },
+            #[cfg(feature = "services-core")]
+            ConnectionType::Native { config } => {
+                if !config.exists() {
+                    errors.push(FieldError::new(
+                        "services-connection.config",
+                        format!("{} does not exist", config.display()),
+                    ))
+                }
+            }
+        }
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.server.tls {
+            #[cfg(unix)]
+            if matches!(self.bind_address(), Ok(BindAddress::Unix(_))) {
+                errors.push(FieldError::new(
+                    "server.tls",
+                    "not supported on unix sockets",
+                ));
+            }
+
+            for (field, file) in [
+                ("server.tls.cert", Some(&tls.cert)),
+                ("server.tls.key", Some(&tls.key)),
+                ("server.tls.client-ca", tls.client_ca.as_ref()),
+            ] {
+                if let Some(file) = file.filter(|file| !file.exists()) {
+                    errors.push(FieldError::new(
+                        field,
+                        format!("{} does not exist", file.display()),
+                    ));
+                }
+            }
+
+            if tls.client_required && tls.client_ca.is_none() {
+                errors.push(FieldError::new(
+                    "server.tls.client-ca",
+                    "required by client-required",
+                ));
+            }
+        }
+
+        self.mongodb.validate(self.strict, &mut errors);
+
+        let rate_limits = self
+            .limits
+            .default
+            .iter()
+            .map(|limit| ("limits.default".to_string(), limit))
+            .chain(
+                self.limits
+                    .callers
+                    .iter()
+                    .map(|(caller, limit)| (format!("limits.callers.{caller}"), limit)),
+            )
+            .chain(
+                self.limits
+                    .routes
+                    .iter()
+                    .map(|(route, limit)| (format!("limits.routes.{route}"), limit)),
+            );
+
+        for (field, limit) in rate_limits {
+            if limit.rate.is_nan() || limit.rate <= 0.0 {
+                errors.push(FieldError::new(
+                    &format!("{field}.rate"),
+                    "must be positive",
+                ));
+            }
+
+            if limit.burst == 0 {
+                errors.push(FieldError::new(&format!("{field}.burst"), "must not be 0"));
+            }
+        }
+
+        if self.limits.in_flight == Some(0) {
+            errors.push(FieldError::new("limits.in-flight", "must not be 0"));
+        }
+
+        if self.removal.purge_interval == 0 {
+            errors.push(FieldError::new("removal.purge-interval", "must not be 0"));
+        }
+
+        if self.auth.tokens.keys().any(String::is_empty) {
+            errors.push(FieldError::new("auth.tokens", "tokens must not be empty"));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(StartupError::Invalid(errors))
+        }
+    }
+}
+
+/// Fields only read at startup, as config paths and JSON pointers.
+const RESTART_FIELDS: [(&str, &str); 8] = [
+    ("port", "/port"),
+    ("mongodb", "/mongodb"),
+    ("removal.purge-interval", "/removal/purge-interval"),
+    ("strict", "/strict"),
+    ("reload", "/reload"),
+    ("log", "/log"),
+    ("server", "/server"),
+    ("watch", "/watch"),
+];
+
+/// Prefix of environment variables overriding config fields, e.g. `PROFILE_MONGODB_PASSWORD`.
+const ENV_PREFIX: &str = "PROFILE";
+
+/// Fields missing from the default config, being unset or below a section that is, each with a
+/// placeholder of its type so environment variables can still set them.
+fn optional_fields() -> Value {
+    json!({
+        "limits": {
+            "in-flight": 0,
+            "default": { "rate": 0, "burst": 0 },
+        },
+        "server": {
+            "bind": "",
+            "tls": {
+                "cert": "",
+                "key": "",
+                "client-ca": "",
+                "client-required": false,
+                "identities": {},
+                "reload-interval": 0,
+            },
+        },
+        "watch": { "name": "" },
+        "mongodb": {
+            "tls": {
+                "ca-file": "",
+                "cert-key-file": "",
+                "allow-invalid-certificates": false,
+            },
+            "replica-set": "",
+            "read-preference": "",
+            "write-concern": { "w": "", "journal": false, "timeout": 0 },
+            "pool": { "max-idle": 0 },
+        },
+    })
+}
+
+#[derive(Clone, Copy)]
+enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+
+    fn parse(self, content: &[u8]) -> Result<Value, String> {
+        match self {
+            Self::Json => serde_json::from_slice(content).map_err(|e| e.to_string()),
+            Self::Toml => std::str::from_utf8(content)
+                .map_err(|e| e.to_string())
+                .and_then(|content| toml::from_str(content).map_err(|e| e.to_string())),
+            Self::Yaml => serde_yaml::from_slice(content).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn write(self, value: &Value) -> Vec<u8> {
+        match self {
+            Self::Json => serde_json::to_vec_pretty(value).unwrap(),
+            // TOML has no null, unset fields are left out instead.
+            Self::Toml => toml::to_string_pretty(&strip_nulls(value.clone()))
+                .unwrap()
+                .into_bytes(),
+            Self::Yaml => serde_yaml::to_string(value).unwrap().into_bytes(),
+        }
+    }
+}
+
+/// Recursively merges objects in `overlay` into `base`, other values replace.
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, strip_nulls(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Overrides fields of `value` from environment variables named after their path, upper cased
+/// with `-` replaced by `_`. A variable suffixed `_FILE` names a file to read the value from.
+/// Values are parsed as JSON unless the field is a string, so whole sections can be given too.
+/// Fields of `optional` missing from `value` are only added when a variable sets them.
+fn apply_env(value: &mut Value, optional: &Value, prefix: &str) -> Result<(), StartupError> {
+    let Value::Object(map) = value else {
+        return Ok(());
+    };
+
+    let placeholders = optional.as_object().cloned().unwrap_or_default();
+    let keys = map
+        .keys()
+        .chain(placeholders.keys())
+        .cloned()
+        .collect::<BTreeSet<_>>();
+
+    for key in keys {
+        let name = format!("{prefix}_{}", key.to_uppercase().replace('-', "_"));
+        let placeholder = placeholders.get(&key).unwrap_or(&Value::Null);
+        let existed = map.contains_key(&key);
+        let field = map.entry(key.clone()).or_insert(Value::Null);
+
+        if let Some(raw) = env_or_file(&name)? {
+            let string = if field.is_null() {
+                placeholder.is_string()
+            } else {
+                field.is_string()
+            };
+
+            *field = if string {
+                Value::String(raw)
+            } else {
+                serde_json::from_str(&raw).unwrap_or(Value::String(raw))
+            };
+        }
+
+        // Unset sections are only created if a variable sets one of their fields.
+        if field.is_null() && placeholder.is_object() {
+            *field = Value::Object(Map::new());
+            apply_env(field, placeholder, &name)?;
+
+            if field.as_object().is_some_and(Map::is_empty) {
+                *field = Value::Null;
+            }
+        } else {
+            apply_env(field, placeholder, &name)?;
+        }
+
+        if !existed && field.is_null() {
+            map.remove(&key);
         }
+    }
 
-        let content = fs::read(path).unwrap();
-        serde_json::from_slice(&content).expect("bad JSON")
+    Ok(())
+}
+
+fn env_or_file(name: &str) -> Result<Option<String>, StartupError> {
+    if let Ok(value) = env::var(name) {
+        return Ok(Some(value));
     }
+
+    let name = format!("{name}_FILE");
+    let Ok(path) = env::var(&name) else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(&path).map_err(|source| StartupError::Secret {
+        name,
+        path: path.into(),
+        source,
+    })?;
+    Ok(Some(content.trim_end_matches(['\r', '\n']).to_string()))
 }
 
 impl MongoConfig {
-    pub fn load(&self) -> (Collection<Profile>, Collection<Document>) {
-        futures::executor::block_on(async {
-            (
-                self.get_collection().await,
-                self.get_collection_docs().await,
-            )
+    /// Connects to the master database, failing if no server answers a ping.
+    pub async fn load(
+        &self,
+        command_events: EventHandler<CommandEvent>,
+    ) -> mongodb::error::Result<Database> {
+        let mut client_opts = ClientOptions::parse(&self.address).await?;
+        client_opts.command_event_handler = Some(command_events);
+
+        if let Some(credential) = self.credential() {
+            client_opts.credential = Some(credential);
+        }
+
+        if let Some(tls) = &self.tls {
+            client_opts.tls = Some(Tls::Enabled(
+                TlsOptions::builder()
+                    .ca_file_path(tls.ca_file.clone())
+                    .cert_key_file_path(tls.cert_key_file.clone())
+                    .allow_invalid_certificates(tls.allow_invalid_certificates)
+                    .build(),
+            ));
+        }
+
+        if let Some(replica_set) = &self.replica_set {
+            client_opts.repl_set_name = Some(replica_set.clone());
+        }
+
+        if let Some(read_preference) = self.read_preference {
+            client_opts.selection_criteria = Some(read_preference.into());
+        }
+
+        if let Some(write_concern) = &self.write_concern {
+            client_opts.write_concern = Some(write_concern.into());
+        }
+
+        client_opts.max_pool_size = Some(self.pool.max_size);
+        client_opts.min_pool_size = Some(self.pool.min_size);
+        client_opts.max_idle_time = self.pool.max_idle.map(Duration::from_secs);
+        client_opts.connect_timeout = Some(Duration::from_millis(self.pool.connect_timeout));
+        client_opts.server_selection_timeout =
+            Some(Duration::from_millis(self.pool.server_selection_timeout));
+
+        let db = Client::with_options(client_opts)?.database(&self.master_db);
+        db.run_command(doc! { "ping": 1 }).await?;
+        Ok(db)
+    }
+
+    fn validate(&self, strict: bool, errors: &mut Vec<FieldError>) {
+        if !self.address.starts_with("mongodb://") && !self.address.starts_with("mongodb+srv://") {
+            errors.push(FieldError::new(
+                "mongodb.address",
+                "must start with mongodb:// or mongodb+srv://",
+            ));
+        }
+
+        if self.master_db.is_empty() {
+            errors.push(FieldError::new("mongodb.masterDB", "must not be empty"));
+        }
+
+        match self.mechanism {
+            MongoAuthMechanism::ScramSha1 | MongoAuthMechanism::ScramSha256 => {
+                if self.username.is_empty() {
+                    errors.push(FieldError::new("mongodb.username", "must not be empty"));
+                }
+
+                if self.password.is_empty() {
+                    errors.push(FieldError::new("mongodb.password", "must not be empty"));
+                } else if strict && self.password == Self::default().password {
+                    errors.push(FieldError::new(
+                        "mongodb.password",
+                        "must not be the default password in strict mode",
+                    ));
+                }
+            }
+            MongoAuthMechanism::X509 => {
+                if self
+                    .tls
+                    .as_ref()
+                    .and_then(|tls| tls.cert_key_file.as_ref())
+                    .is_none()
+                {
+                    errors.push(FieldError::new(
+                        "mongodb.tls.cert-key-file",
+                        "required by MONGODB-X509",
+                    ));
+                }
+            }
+            MongoAuthMechanism::None => {}
+        }
+
+        if let Some(tls) = &self.tls {
+            for (field, file) in [
+                ("mongodb.tls.ca-file", &tls.ca_file),
+                ("mongodb.tls.cert-key-file", &tls.cert_key_file),
+            ] {
+                if let Some(file) = file.as_ref().filter(|file| !file.exists()) {
+                    errors.push(FieldError::new(
+                        field,
+                        format!("{} does not exist", file.display()),
+                    ));
+                }
+            }
+        }
+
+        if self.replica_set.as_ref().is_some_and(String::is_empty) {
+            errors.push(FieldError::new("mongodb.replica-set", "must not be empty"));
+        }
+
+        if let Some(w) = self.write_concern.as_ref().and_then(|wc| wc.w.as_ref()) {
+            if w.is_empty() {
+                errors.push(FieldError::new(
+                    "mongodb.write-concern.w",
+                    "must not be empty",
+                ));
+            }
+        }
+
+        if self.pool.max_size == 0 {
+            errors.push(FieldError::new("mongodb.pool.max-size", "must not be 0"));
+        }
+
+        if self.pool.min_size > self.pool.max_size {
+            errors.push(FieldError::new(
+                "mongodb.pool.min-size",
+                "must not exceed max-size",
+            ));
+        }
+    }
+
+    fn credential(&self) -> Option<Credential> {
+        let mechanism = match self.mechanism {
+            MongoAuthMechanism::None => return None,
+            MongoAuthMechanism::ScramSha1 => AuthMechanism::ScramSha1,
+            MongoAuthMechanism::ScramSha256 => AuthMechanism::ScramSha256,
+            MongoAuthMechanism::X509 => {
+                return Some(
+                    Credential::builder()
+                        .username((!self.username.is_empty()).then(|| self.username.clone()))
+                        .mechanism(AuthMechanism::MongoDbX509)
+                        .build(),
+                )
+            }
+        };
+
+        Some(
+            Credential::builder()
+                .username(self.username.clone())
+                .password(self.password.clone())
+                .mechanism(mechanism)
+                .source(self.auth_db.clone())
+                .build(),
+        )
+    }
+}
+
+impl From<MongoReadPreference> for SelectionCriteria {
+    fn from(value: MongoReadPreference) -> Self {
+        SelectionCriteria::ReadPreference(match value {
+            MongoReadPreference::Primary => ReadPreference::Primary,
+            MongoReadPreference::PrimaryPreferred => {
+                ReadPreference::PrimaryPreferred { options: None }
+            }
+            MongoReadPreference::Secondary => ReadPreference::Secondary { options: None },
+            MongoReadPreference::SecondaryPreferred => {
+                ReadPreference::SecondaryPreferred { options: None }
+            }
+            MongoReadPreference::Nearest => ReadPreference::Nearest { options: None },
         })
     }
+}
 
-    async fn get_collection(&self) -> Collection<Profile> {
-        let mut client_opts = ClientOptions::parse(&self.address).await.unwrap();
+impl From<&MongoWriteConcern> for WriteConcern {
+    fn from(value: &MongoWriteConcern) -> Self {
+        WriteConcern::builder()
+            .w(value.w.as_ref().map(|w| match w.parse::<u32>() {
+                Ok(nodes) => Acknowledgment::Nodes(nodes),
+                Err(_) => Acknowledgment::from(w.as_str()),
+            }))
+            .journal(value.journal)
+            .w_timeout(value.timeout.map(Duration::from_millis))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applies variables under `prefix`, unique per test as the environment is shared.
+    fn with_env(prefix: &str, vars: &[(&str, &str)]) -> MasterConfig {
+        for (name, value) in vars {
+            env::set_var(format!("{prefix}_{name}"), value);
+        }
 
-        let scram_sha_1_cred = Credential::builder()
-            .username(self.username.clone())
-            .password(self.password.clone())
-            .mechanism(AuthMechanism::ScramSha1)
-            .source(self.auth_db.clone())
-            .build();
+        let mut value = serde_json::to_value(MasterConfig::default()).unwrap();
+        apply_env(&mut value, &optional_fields(), prefix).unwrap();
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn env_overrides_fields() {
+        let config = with_env(
+            "PROFILE_TEST_FIELDS",
+            &[("PORT", "9000"), ("MONGODB_PASSWORD", "1234")],
+        );
 
-        client_opts.credential = Some(scram_sha_1_cred);
-        let client = Client::with_options(client_opts).unwrap();
-        client.database(&self.master_db).collection("profile")
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.mongodb.password, "1234");
     }
 
-    async fn get_collection_docs(&self) -> Collection<Document> {
-        let mut client_opts = ClientOptions::parse(&self.address).await.unwrap();
+    #[test]
+    fn env_creates_unset_sections() {
+        let config = with_env(
+            "PROFILE_TEST_SECTIONS",
+            &[
+                ("MONGODB_TLS_CA_FILE", "/etc/ca.pem"),
+                ("MONGODB_REPLICA_SET", "rs0"),
+                ("MONGODB_WRITE_CONCERN_JOURNAL", "true"),
+                ("LIMITS_DEFAULT", r#"{ "rate": 5, "burst": 10 }"#),
+                ("WATCH_NAME", "1"),
+            ],
+        );
+
+        let tls = config.mongodb.tls.unwrap();
+        assert_eq!(tls.ca_file, Some(PathBuf::from("/etc/ca.pem")));
+        assert!(!tls.allow_invalid_certificates);
+        assert_eq!(config.mongodb.replica_set.as_deref(), Some("rs0"));
+        assert_eq!(config.mongodb.write_concern.unwrap().journal, Some(true));
+        assert_eq!(config.limits.default.unwrap().burst, 10);
+        assert_eq!(config.watch.name.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn reload_keeps_restart_fields() {
+        let current = MasterConfig::default();
+        let mut new = MasterConfig {
+            port: 9000,
+            ..Default::default()
+        };
+        new.mongodb.password = "changed".to_string();
+        new.removal.purge_interval = 10;
+        new.removal.retention = 20;
+        new.auth.trust_anonymous = false;
+
+        let (kept, changed) = new.keep_restart_fields(&current);
+
+        assert_eq!(changed, ["port", "mongodb", "removal.purge-interval"]);
+        assert_eq!(kept.port, current.port);
+        assert_eq!(kept.mongodb.password, current.mongodb.password);
+        assert_eq!(kept.removal.purge_interval, current.removal.purge_interval);
+        assert_eq!(kept.removal.retention, 20);
+        assert!(!kept.auth.trust_anonymous);
+    }
+
+    #[test]
+    fn reload_without_restart_changes() {
+        let (_, changed) = MasterConfig::default().keep_restart_fields(&MasterConfig::default());
+        assert!(changed.is_empty());
+    }
 
-        let scram_sha_1_cred = Credential::builder()
-            .username(self.username.clone())
-            .password(self.password.clone())
-            .mechanism(AuthMechanism::ScramSha1)
-            .source(self.auth_db.clone())
-            .build();
+    #[test]
+    fn env_leaves_unset_sections_alone() {
+        let mut value = serde_json::to_value(MasterConfig::default()).unwrap();
+        let default = value.clone();
+        apply_env(&mut value, &optional_fields(), "PROFILE_TEST_UNSET").unwrap();
 
-        client_opts.credential = Some(scram_sha_1_cred);
-        let client = Client::with_options(client_opts).unwrap();
-        client.database(&self.master_db).collection("profile")
+        assert_eq!(value, default);
     }
 }
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use tracing::{instrument, Level};
+
+use crate::{instance::ProfileInstance, Caller, Profile};
+
+macro_rules! denied {
+    () => {
+        Err(mongodb::error::Error::custom("access denied".to_string()))
+    };
+}
+
+/// Default values registered by services, used when a key is missing from every overlay layer.
+///
+/// Stored as one document per service: `{ _id: service, values: { key: value } }`.
+pub struct Defaults;
+
+impl Defaults {
+    async fn set_int(
+        instance: &ProfileInstance,
+        service: &str,
+        entries: Vec<(String, String)>,
+    ) -> Result<(), mongodb::error::Error> {
+        Profile::check_service(instance, service).await?;
+
+        let mut m_set = Document::new();
+        let mut m_unset = Document::new();
+
+        for (k, v) in entries.iter() {
+            if v.is_empty() {
+                m_unset.insert(format!("values.{}", Profile::encode(k)), v.to_string());
+            } else {
+                m_set.insert(format!("values.{}", Profile::encode(k)), v.to_string());
+            }
+        }
+
+        instance
+            .defaults
+            .update_one(
+                doc! { "_id": service },
+                doc! { "$set": m_set, "$unset": m_unset },
+            )
+            .upsert(true)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_int(
+        instance: &ProfileInstance,
+        service: &str,
+    ) -> Result<BTreeMap<String, String>, mongodb::error::Error> {
+        Ok(instance
+            .defaults
+            .find_one(doc! { "_id": service })
+            .await?
+            .and_then(|defaults| defaults.get_document("values").ok().cloned())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(k, v)| {
+                (
+                    Profile::decode(&k),
+                    v.as_str().unwrap_or_default().to_string(),
+                )
+            })
+            .collect())
+    }
+
+    /// Reads the still encoded defaults of all `services` at once.
+    pub(crate) async fn get_many(
+        instance: &ProfileInstance,
+        services: &[&str],
+    ) -> Result<BTreeMap<String, Document>, mongodb::error::Error> {
+        if services.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let mut cursor = instance
+            .defaults
+            .find(doc! { "_id": { "$in": services } })
+            .await?;
+        let mut out = BTreeMap::new();
+
+        while let Some(defaults) = cursor.try_next().await? {
+            if let (Ok(service), Ok(values)) =
+                (defaults.get_str("_id"), defaults.get_document("values"))
+            {
+                out.insert(service.to_string(), values.clone());
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl Defaults {
+    /// Sets defaults of `service`, which only the service itself and admins may do.
+    #[instrument(skip_all, fields(service = %service, caller = ?caller), err(level = Level::WARN))]
+    pub async fn set(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        service: &str,
+        entries: Vec<(String, String)>,
+    ) -> Result<(), mongodb::error::Error> {
+        if !caller.owns(service) && !caller.is_admin(instance) {
+            return denied!();
+        }
+
+        Self::set_int(instance, service, entries).await
+    }
+
+    #[instrument(skip_all, fields(service = %service), err(level = Level::WARN))]
+    pub async fn show(
+        instance: &ProfileInstance,
+        service: &str,
+    ) -> Result<BTreeMap<String, String>, mongodb::error::Error> {
+        Self::get_int(instance, service).await
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::BTreeSet;
+
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use tracing::{instrument, Level};
+
+use crate::{
+    instance::ProfileInstance,
+    schema::{Access, Grant},
+    Caller, Profile,
+};
+
+macro_rules! denied {
+    () => {
+        Err(mongodb::error::Error::custom("access denied".to_string()))
+    };
+}
+
+/// Access a service has granted other services on keys of its namespace.
+///
+/// Stored as one document per pair: `{ _id: { owner, grantee }, read: [key], write: [key] }`,
+/// where write access implies read access.
+pub struct Grants;
+
+impl Grants {
+    fn id(owner: &str, grantee: &str) -> Document {
+        doc! { "_id": { "owner": owner, "grantee": grantee } }
+    }
+
+    fn keys(grant: &Document, field: &str) -> BTreeSet<String> {
+        grant
+            .get_array(field)
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(|key| key.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn find(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+    ) -> Result<Option<Document>, mongodb::error::Error> {
+        match caller {
+            Caller::Trusted => Ok(None),
+            Caller::Service(grantee) => instance.grants.find_one(Self::id(owner, grantee)).await,
+        }
+    }
+
+    /// Keys of `owner` the caller may read, `None` if it may read all of them.
+    pub(crate) async fn readable(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+    ) -> Result<Option<BTreeSet<String>>, mongodb::error::Error> {
+        if caller.owns(owner) {
+            return Ok(None);
+        }
+
+        let grant = Self::find(instance, caller, owner)
+            .await?
+            .unwrap_or_default();
+        let mut keys = Self::keys(&grant, "read");
+        keys.extend(Self::keys(&grant, "write"));
+        Ok(Some(keys))
+    }
+
+    /// Keys of `owner` the caller may write, `None` if it may write all of them.
+    pub(crate) async fn writable(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+    ) -> Result<Option<BTreeSet<String>>, mongodb::error::Error> {
+        if caller.owns(owner) {
+            return Ok(None);
+        }
+
+        let grant = Self::find(instance, caller, owner)
+            .await?
+            .unwrap_or_default();
+        Ok(Some(Self::keys(&grant, "write")))
+    }
+
+    async fn grant_int(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+        grantee: &str,
+        keys: Vec<String>,
+        access: Access,
+    ) -> Result<(), mongodb::error::Error> {
+        if !caller.owns(owner) {
+            return denied!();
+        }
+
+        Profile::check_service(instance, owner).await?;
+        Profile::check_service(instance, grantee).await?;
+
+        let update = match access {
+            Access::Read => doc! { "$addToSet": { "read": { "$each": &keys } } },
+            Access::ReadWrite => doc! {
+                "$addToSet": {
+                    "read": { "$each": &keys },
+                    "write": { "$each": &keys },
+                }
+            },
+        };
+
+        instance
+            .grants
+            .update_one(Self::id(owner, grantee), update)
+            .upsert(true)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_int(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+        grantee: &str,
+        keys: Vec<String>,
+    ) -> Result<(), mongodb::error::Error> {
+        if !caller.owns(owner) {
+            return denied!();
+        }
+
+        instance
+            .grants
+            .update_one(
+                Self::id(owner, grantee),
+                doc! {
+                    "$pull": {
+                        "read": { "$in": &keys },
+                        "write": { "$in": &keys },
+                    }
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_int(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+    ) -> Result<Vec<Grant>, mongodb::error::Error> {
+        if !caller.owns(owner) {
+            return denied!();
+        }
+
+        let mut cursor = instance.grants.find(doc! { "_id.owner": owner }).await?;
+        let mut out = Vec::new();
+
+        while let Some(grant) = cursor.try_next().await? {
+            let Ok(grantee) = grant
+                .get_document("_id")
+                .and_then(|id| id.get_str("grantee"))
+            else {
+                continue;
+            };
+
+            out.push(Grant {
+                grantee: grantee.to_string(),
+                read: Self::keys(&grant, "read"),
+                write: Self::keys(&grant, "write"),
+            });
+        }
+
+        Ok(out)
+    }
+}
+
+impl Grants {
+    #[instrument(skip_all, fields(owner = %owner, grantee = %grantee, caller = ?caller), err(level = Level::WARN))]
+    pub async fn grant(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+        grantee: &str,
+        keys: Vec<String>,
+        access: Access,
+    ) -> Result<(), mongodb::error::Error> {
+        Self::grant_int(instance, caller, owner, grantee, keys, access).await
+    }
+
+    #[instrument(skip_all, fields(owner = %owner, grantee = %grantee, caller = ?caller), err(level = Level::WARN))]
+    pub async fn revoke(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+        grantee: &str,
+        keys: Vec<String>,
+    ) -> Result<(), mongodb::error::Error> {
+        Self::revoke_int(instance, caller, owner, grantee, keys).await
+    }
+
+    #[instrument(skip_all, fields(owner = %owner, caller = ?caller), err(level = Level::WARN))]
+    pub async fn show(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        owner: &str,
+    ) -> Result<Vec<Grant>, mongodb::error::Error> {
+        Self::get_int(instance, caller, owner).await
+    }
+}
@@ -1,32 +1,110 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
 
 use async_trait::async_trait;
 #[cfg(feature = "services-core")]
 use atom_services::ServiceInstance;
 use dyn_clone::DynClone;
-use mongodb::{bson::Document, Collection};
+use mongodb::{
+    bson::{doc, Document},
+    Collection,
+};
 #[cfg(feature = "services-request")]
 use reqwest::{StatusCode, Url};
+#[cfg(unix)]
+use tokio::signal::unix::SignalKind;
+use tokio::sync::broadcast;
+use tracing::{error, info, instrument, warn};
 
-use crate::{ConnectionType, MasterConfig, Profile};
+#[cfg(feature = "services-request")]
+use crate::FieldError;
+use crate::{
+    metrics::MeteredServices, ConnectionType, Limiter, MasterConfig, Metrics, Profile,
+    ProfileCache, ProfileEvent, SchemaCache, StartupError, EVENT_BACKLOG,
+};
+#[cfg(feature = "services-request")]
+use crate::{TraceContext, TRACEPARENT, TRACESTATE};
 
 #[derive(Clone)]
 pub struct ProfileInstance {
-    pub config: MasterConfig,
+    path: PathBuf,
+    live: Arc<RwLock<Arc<LiveState>>>,
     pub profiles: Collection<Profile>,
     pub profiles_doc: Collection<Document>,
+    pub defaults: Collection<Document>,
+    pub schemas: Collection<Document>,
+    pub grants: Collection<Document>,
+    pub resume_tokens: Collection<Document>,
+    pub metrics: Metrics,
+    pub limiter: Arc<Limiter>,
+    pub cache: Arc<ProfileCache>,
+    pub schema_cache: Arc<SchemaCache>,
+    pub(crate) changes: broadcast::Sender<ProfileEvent>,
+}
+
+/// Config and services client in use, replaced as a whole when the config is reloaded.
+pub struct LiveState {
+    pub config: MasterConfig,
     pub services: Box<dyn ProfileServiceFunctions>,
+    /// When each service was last found to exist, trusted for `cache.services-ttl`.
+    pub(crate) known_services: Mutex<HashMap<String, Instant>>,
+}
+
+impl LiveState {
+    fn new(config: MasterConfig, services: Box<dyn ProfileServiceFunctions>) -> Self {
+        Self {
+            config,
+            services,
+            known_services: Mutex::default(),
+        }
+    }
 }
 
 impl ProfileInstance {
-    pub fn load(config: &Path) -> Self {
-        let config = MasterConfig::read(config);
-        let (profiles, profiles_doc) = config.mongodb.load();
+    pub async fn load(path: &Path) -> Result<Self, StartupError> {
+        let config = MasterConfig::read_or_create(path)?;
+        config.validate()?;
+        Self::with_config(path, config).await
+    }
+
+    /// Connects with a config already read from `path`, which is reread on reload.
+    pub async fn with_config(path: &Path, config: MasterConfig) -> Result<Self, StartupError> {
+        let metrics = Metrics::new();
+        let db = config.mongodb.load(metrics.command_events()).await?;
+        let services = Self::connect_services(&config, &metrics)?;
 
-        let services: Box<dyn ProfileServiceFunctions> = match &config.services_connection {
+        Ok(ProfileInstance {
+            path: path.to_path_buf(),
+            live: Arc::new(RwLock::new(Arc::new(LiveState::new(config, services)))),
+            profiles: db.collection("profile"),
+            profiles_doc: db.collection("profile"),
+            defaults: db.collection("profile-defaults"),
+            schemas: db.collection("profile-schema"),
+            grants: db.collection("profile-grants"),
+            resume_tokens: db.collection("profile-resume-tokens"),
+            metrics,
+            limiter: Arc::default(),
+            cache: Arc::default(),
+            schema_cache: Arc::default(),
+            changes: broadcast::channel(EVENT_BACKLOG).0,
+        })
+    }
+
+    fn connect_services(
+        config: &MasterConfig,
+        metrics: &Metrics,
+    ) -> Result<Box<dyn ProfileServiceFunctions>, StartupError> {
+        let inner: Box<dyn ProfileServiceFunctions> = match &config.services_connection {
             #[cfg(feature = "services-request")]
             ConnectionType::Http { address } => Box::new(ProfileServiceFunctionsRequest::new(
-                Url::parse(address).unwrap(),
+                Url::parse(address).map_err(|e| {
+                    StartupError::Invalid(vec![FieldError::new("services-connection.address", e)])
+                })?,
             )),
             #[cfg(feature = "services-core")]
             ConnectionType::Native { config } => Box::new(ProfileServiceFunctionsCore::new(
@@ -34,12 +112,126 @@ impl ProfileInstance {
             )),
         };
 
-        ProfileInstance {
-            config,
-            profiles,
-            profiles_doc,
-            services,
+        Ok(Box::new(MeteredServices {
+            inner,
+            metrics: metrics.clone(),
+        }))
+    }
+
+    /// Checks that MongoDB answers.
+    pub async fn ping_storage(&self) -> mongodb::error::Result<()> {
+        self.profiles_doc
+            .client()
+            .database("admin")
+            .run_command(doc! { "ping": 1 })
+            .await
+            .map(|_| ())
+    }
+
+    /// The current config and services client, unaffected by reloads while held.
+    pub fn live(&self) -> Arc<LiveState> {
+        self.live.read().unwrap().clone()
+    }
+
+    /// Rereads the config file and swaps in the new config and services client, returning the
+    /// changed fields that only take effect after a restart and are kept as they were. Fails
+    /// without changing anything if the file is missing or invalid, it is never recreated here.
+    pub fn reload(&self) -> Result<Vec<&'static str>, StartupError> {
+        let config = MasterConfig::read(&self.path)?;
+        config.validate()?;
+
+        let (config, restart) = config.keep_restart_fields(&self.live().config);
+        let services = Self::connect_services(&config, &self.metrics)?;
+        *self.live.write().unwrap() = Arc::new(LiveState::new(config, services));
+        self.schema_cache.clear();
+        Ok(restart)
+    }
+
+    /// Spawns the task reloading the config when its file changes or on SIGHUP.
+    pub fn spawn_reload(&self) -> tokio::task::JoinHandle<()> {
+        let instance = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                instance.live().config.reload.interval.max(1),
+            ));
+            let mut modified = Self::modified(&instance.path);
+
+            #[cfg(unix)]
+            let mut signal = tokio::signal::unix::signal(SignalKind::hangup()).ok();
+            #[cfg(not(unix))]
+            let mut signal = None;
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let current = Self::modified(&instance.path);
+
+                        if current == modified {
+                            continue;
+                        }
+
+                        modified = current;
+                    }
+                    _ = Self::hangup(&mut signal) => {}
+                }
+
+                match instance.reload() {
+                    Ok(restart) => {
+                        info!(path = %instance.path.display(), "reloaded config");
+
+                        for field in restart {
+                            warn!(field, "config field changed, restart to apply");
+                        }
+                    }
+                    Err(e) => {
+                        error!(error = %e, "failed to reload config, keeping the current one")
+                    }
+                }
+            }
+        })
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    /// Resolves on the next SIGHUP, never if signals aren't available.
+    #[cfg(unix)]
+    async fn hangup(signal: &mut Option<tokio::signal::unix::Signal>) {
+        if let Some(signal) = signal {
+            if signal.recv().await.is_some() {
+                return;
+            }
         }
+
+        std::future::pending().await
+    }
+
+    #[cfg(not(unix))]
+    async fn hangup(_: &mut Option<()>) {
+        std::future::pending().await
+    }
+
+    /// Spawns the task purging removed profiles once their retention period is over.
+    pub fn spawn_purge(&self) -> tokio::task::JoinHandle<()> {
+        let instance = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                instance.live().config.removal.purge_interval.max(1),
+            ));
+
+            loop {
+                interval.tick().await;
+
+                match Profile::purge(&instance).await {
+                    Ok(0) => {}
+                    Ok(purged) => info!(purged, "purged removed profiles"),
+                    Err(e) => error!(error = %e, "failed to purge removed profiles"),
+                }
+            }
+        })
     }
 }
 
@@ -54,6 +246,18 @@ pub trait ProfileServiceFunctions: DynClone + Send + Sync {
         &self,
         req: atom_services::schema::ShowReq,
     ) -> (u16, atom_services::schema::ShowRes);
+
+    /// Checks that atom-services can be reached, by default with an `exists` call.
+    async fn ready(&self) -> Result<(), String> {
+        match self
+            .exists(atom_services::schema::ExistsReq { id: String::new() })
+            .await
+        {
+            (status, _) if status < 500 => Ok(()),
+            (_, atom_services::schema::ExistsRes::Error { reason }) => Err(reason),
+            (status, _) => Err(format!("services responded with {status}")),
+        }
+    }
 }
 
 dyn_clone::clone_trait_object!(ProfileServiceFunctions);
@@ -74,6 +278,7 @@ impl ProfileServiceFunctionsCore {
 #[cfg(feature = "services-core")]
 #[async_trait]
 impl ProfileServiceFunctions for ProfileServiceFunctionsCore {
+    #[instrument(skip_all, fields(service = %req.id))]
     async fn exists(
         &self,
         req: atom_services::schema::ExistsReq,
@@ -82,6 +287,7 @@ impl ProfileServiceFunctions for ProfileServiceFunctionsCore {
         (res.status().as_u16(), res)
     }
 
+    #[instrument(skip_all, fields(service = %req.id))]
     async fn show(
         &self,
         req: atom_services::schema::ShowReq,
@@ -104,6 +310,7 @@ macro_rules! catch_fail {
         match $x {
             Ok(k) => k,
             Err(e) => {
+                warn!(error = %e, "services call failed");
                 return (
                     e.status()
                         .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
@@ -125,21 +332,32 @@ impl ProfileServiceFunctionsRequest {
             reqwest: reqwest::Client::new(),
         }
     }
+
+    /// POST to the services API, propagating the trace context of the current request.
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        let mut builder = self.reqwest.post(self.services.join(path).unwrap());
+
+        if let Some(context) = TraceContext::current() {
+            builder = builder.header(TRACEPARENT, context.child());
+
+            if let Some(state) = context.state {
+                builder = builder.header(TRACESTATE, state);
+            }
+        }
+
+        builder
+    }
 }
 
 #[cfg(feature = "services-request")]
 #[async_trait]
 impl ProfileServiceFunctions for ProfileServiceFunctionsRequest {
+    #[instrument(skip_all, fields(service = %req.id))]
     async fn exists(
         &self,
         req: atom_services::schema::ExistsReq,
     ) -> (u16, atom_services::schema::ExistsRes) {
-        let res = self
-            .reqwest
-            .post(self.services.join("/api/services/v1/exists").unwrap())
-            .json(&req)
-            .send()
-            .await;
+        let res = self.post("/api/services/v1/exists").json(&req).send().await;
 
         let res = catch_fail!(ExistsRes, res);
         (
@@ -148,16 +366,12 @@ impl ProfileServiceFunctions for ProfileServiceFunctionsRequest {
         )
     }
 
+    #[instrument(skip_all, fields(service = %req.id))]
     async fn show(
         &self,
         req: atom_services::schema::ShowReq,
     ) -> (u16, atom_services::schema::ShowRes) {
-        let res = self
-            .reqwest
-            .post(self.services.join("/api/services/v1/show").unwrap())
-            .json(&req)
-            .send()
-            .await;
+        let res = self.post("/api/services/v1/show").json(&req).send().await;
 
         let res = catch_fail!(ShowRes, res);
         (
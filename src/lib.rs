@@ -15,11 +15,74 @@ mod profile;
 #[cfg(feature = "core")]
 pub use profile::*;
 
+#[cfg(feature = "core")]
+mod changes;
+#[cfg(feature = "core")]
+pub use changes::*;
+
 #[cfg(feature = "core")]
 mod config;
 #[cfg(feature = "core")]
 pub use config::*;
 
+#[cfg(feature = "core")]
+mod cache;
+#[cfg(feature = "core")]
+pub use cache::*;
+
+#[cfg(feature = "core")]
+mod caller;
+#[cfg(feature = "core")]
+pub use caller::*;
+
+#[cfg(feature = "core")]
+mod defaults;
+#[cfg(feature = "core")]
+pub use defaults::*;
+
+#[cfg(feature = "core")]
+mod grants;
+#[cfg(feature = "core")]
+pub use grants::*;
+
+#[cfg(feature = "core")]
+mod limits;
+#[cfg(feature = "core")]
+pub use limits::*;
+
+#[cfg(feature = "core")]
+mod metrics;
+#[cfg(feature = "core")]
+pub use metrics::*;
+
+#[cfg(feature = "core")]
+mod registry;
+#[cfg(feature = "core")]
+pub use registry::*;
+
+#[cfg(feature = "core")]
+mod startup;
+#[cfg(feature = "core")]
+pub use startup::*;
+
+#[cfg(feature = "core")]
+mod template;
+
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(feature = "tls")]
+pub use tls::*;
+
+#[cfg(feature = "core")]
+mod trace;
+#[cfg(feature = "core")]
+pub use trace::*;
+
+#[cfg(feature = "core")]
+mod transfer;
+#[cfg(feature = "core")]
+pub use transfer::*;
+
 #[cfg(feature = "core")]
 mod router;
 #[cfg(feature = "core")]
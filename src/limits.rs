@@ -0,0 +1,244 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header::RETRY_AFTER, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::{instance::ProfileInstance, Caller, LimitsConfig, RateLimit};
+
+/// Token buckets and in-flight count shared by every request of an instance.
+#[derive(Default)]
+pub struct Limiter {
+    /// Keyed by caller, and by caller and route for route limits.
+    buckets: Mutex<HashMap<(String, Option<String>), Bucket>>,
+    in_flight: AtomicUsize,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Decrements the in-flight count when the request is done.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Limiter {
+    fn enter(&self, cap: Option<usize>) -> Option<InFlight<'_>> {
+        let count = self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let guard = InFlight(&self.in_flight);
+
+        match cap {
+            Some(cap) if count >= cap => None,
+            _ => Some(guard),
+        }
+    }
+
+    /// Takes a token from the bucket, or returns the seconds until one is available.
+    fn take(&self, key: (String, Option<String>), limit: &RateLimit) -> Result<(), u64> {
+        self.take_at(key, limit, Instant::now())
+    }
+
+    fn take_at(
+        &self,
+        key: (String, Option<String>),
+        limit: &RateLimit,
+        now: Instant,
+    ) -> Result<(), u64> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: limit.burst as f64,
+            updated: now,
+        });
+
+        bucket.tokens = (bucket.tokens
+            + now.duration_since(bucket.updated).as_secs_f64() * limit.rate)
+            .min(limit.burst as f64);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / limit.rate).ceil().max(1.0) as u64)
+        }
+    }
+
+    /// Takes a token for the caller and one for the caller on `route` where limits apply, or
+    /// returns the seconds until one is available.
+    fn check(&self, limits: &LimitsConfig, caller: &str, route: &str) -> Result<(), u64> {
+        if let Some(limit) = limits.callers.get(caller).or(limits.default.as_ref()) {
+            self.take((caller.to_string(), None), limit)?;
+        }
+
+        if let Some(limit) = limits.routes.get(route) {
+            self.take((caller.to_string(), Some(route.to_string())), limit)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Identity rate limits are kept under. Callers the handler may turn away share `anonymous`,
+/// as not every route checks the caller.
+fn limit_key<E>(caller: Result<Caller, E>) -> String {
+    match caller {
+        Ok(Caller::Service(name)) => name,
+        Ok(Caller::Trusted) => "trusted".to_string(),
+        Err(_) => "anonymous".to_string(),
+    }
+}
+
+fn reject(status: StatusCode, retry_after: u64, reason: &str) -> Response {
+    (
+        status,
+        [(RETRY_AFTER, retry_after.to_string())],
+        Json(json!({ "type": "error", "reason": reason })),
+    )
+        .into_response()
+}
+
+/// Middleware shedding requests over the in-flight cap with 503 and ones over the caller's rate
+/// limits with 429, both with `Retry-After`.
+pub(crate) async fn limit(
+    State(instance): State<ProfileInstance>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let live = instance.live();
+    let limits = &live.config.limits;
+
+    let Some(_in_flight) = instance.limiter.enter(limits.in_flight) else {
+        return reject(
+            StatusCode::SERVICE_UNAVAILABLE,
+            1,
+            "too many requests in flight",
+        );
+    };
+
+    let (mut parts, body) = request.into_parts();
+    // Relative to where the router is nested, such as `/set`.
+    let route = parts.uri.path().to_string();
+    let caller = limit_key(Caller::from_request_parts(&mut parts, &instance).await);
+
+    if let Err(retry_after) = instance.limiter.check(limits, &caller, &route) {
+        return reject(StatusCode::TOO_MANY_REQUESTS, retry_after, "rate limited");
+    }
+
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn key(caller: &str) -> (String, Option<String>) {
+        (caller.to_string(), None)
+    }
+
+    #[test]
+    fn take_spends_burst() {
+        let limiter = Limiter::default();
+        let limit = RateLimit {
+            rate: 1.0,
+            burst: 2,
+        };
+        let now = Instant::now();
+
+        assert_eq!(limiter.take_at(key("a"), &limit, now), Ok(()));
+        assert_eq!(limiter.take_at(key("a"), &limit, now), Ok(()));
+        assert_eq!(limiter.take_at(key("a"), &limit, now), Err(1));
+        // Buckets are kept per key.
+        assert_eq!(limiter.take_at(key("b"), &limit, now), Ok(()));
+    }
+
+    #[test]
+    fn take_refills_up_to_burst() {
+        let limiter = Limiter::default();
+        let limit = RateLimit {
+            rate: 2.0,
+            burst: 3,
+        };
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.take_at(key("a"), &limit, now), Ok(()));
+        }
+
+        assert!(limiter.take_at(key("a"), &limit, now).is_err());
+        assert_eq!(
+            limiter.take_at(key("a"), &limit, now + Duration::from_millis(500)),
+            Ok(())
+        );
+
+        let later = now + Duration::from_secs(60);
+
+        for _ in 0..3 {
+            assert_eq!(limiter.take_at(key("a"), &limit, later), Ok(()));
+        }
+
+        assert!(limiter.take_at(key("a"), &limit, later).is_err());
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_seconds() {
+        let limiter = Limiter::default();
+        let limit = RateLimit {
+            rate: 0.25,
+            burst: 1,
+        };
+        let now = Instant::now();
+
+        assert_eq!(limiter.take_at(key("a"), &limit, now), Ok(()));
+        assert_eq!(limiter.take_at(key("a"), &limit, now), Err(4));
+        assert_eq!(
+            limiter.take_at(key("a"), &limit, now + Duration::from_secs(2)),
+            Err(2)
+        );
+        assert_eq!(
+            limiter.take_at(key("a"), &limit, now + Duration::from_millis(3900)),
+            Err(1)
+        );
+    }
+
+    #[test]
+    fn callers_without_token_are_limited() {
+        let limiter = Limiter::default();
+        let limits = LimitsConfig {
+            routes: [(
+                "/set".to_string(),
+                RateLimit {
+                    rate: 1.0,
+                    burst: 1,
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let caller = limit_key(Err::<Caller, _>("missing token"));
+
+        assert_eq!(caller, "anonymous");
+        assert_eq!(limiter.check(&limits, &caller, "/set"), Ok(()));
+        assert_eq!(limiter.check(&limits, &caller, "/set"), Err(1));
+        assert_eq!(limiter.check(&limits, &caller, "/show"), Ok(()));
+    }
+}
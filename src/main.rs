@@ -1,21 +1,327 @@
+#[cfg(all(feature = "core", unix))]
+use std::os::unix::fs::FileTypeExt;
 #[cfg(feature = "core")]
-use std::path::PathBuf;
+use std::{
+    collections::BTreeSet,
+    fs,
+    future::{Future, IntoFuture},
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
 
 #[cfg(feature = "core")]
-use atom_profile::{ProfileInstance, Router};
+use tokio::{
+    fs::File,
+    io::{self, AsyncBufRead, AsyncWrite, BufReader, BufWriter},
+    net::TcpListener,
+    sync::oneshot,
+};
+#[cfg(all(feature = "core", unix))]
+use tokio::{
+    net::UnixListener,
+    signal::unix::{signal, SignalKind},
+};
+#[cfg(feature = "core")]
+use tracing::{error, info, warn};
+
+#[cfg(feature = "core")]
+use atom_profile::{
+    init_logging, BindAddress, FieldError, ImportMode, MasterConfig, Profile, ProfileInstance,
+    Router, StartupError, TransferFilter,
+};
+#[cfg(feature = "tls")]
+use atom_profile::{TlsListener, TlsPeer};
+
+#[cfg(feature = "core")]
+const USAGE: &str = "usage:
+    atom-profile
+    atom-profile --check-config
+    atom-profile export [--service NAME]... [--no-bucket] [--output FILE]
+    atom-profile import [--service NAME]... [--no-bucket] [--input FILE] [--replace] [--dry-run]
+    atom-profile migrate-template [--dry-run]";
+
+#[cfg(feature = "core")]
+struct TransferArgs {
+    filter: TransferFilter,
+    file: Option<PathBuf>,
+    replace: bool,
+    dry_run: bool,
+}
+
+#[cfg(feature = "core")]
+impl TransferArgs {
+    fn parse(mut args: impl Iterator<Item = String>, file_flag: &str) -> Result<Self, String> {
+        let mut out = Self {
+            filter: TransferFilter::default(),
+            file: None,
+            replace: false,
+            dry_run: false,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--service" => {
+                    let service = args.next().ok_or("--service requires a value")?;
+                    out.filter
+                        .services
+                        .get_or_insert_with(BTreeSet::new)
+                        .insert(service);
+                }
+                "--no-bucket" => out.filter.bucket = false,
+                "--replace" if file_flag == "--input" => out.replace = true,
+                "--dry-run" if file_flag == "--input" => out.dry_run = true,
+                flag if flag == file_flag => {
+                    out.file = Some(
+                        args.next()
+                            .ok_or(format!("{flag} requires a value"))?
+                            .into(),
+                    )
+                }
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "core")]
+async fn run_command(
+    instance: ProfileInstance,
+    command: &str,
+    args: impl Iterator<Item = String>,
+) -> Result<(), String> {
+    match command {
+        "export" => {
+            let args = TransferArgs::parse(args, "--output")?;
+            let out: Box<dyn AsyncWrite + Unpin + Send> = match &args.file {
+                Some(path) => Box::new(BufWriter::new(
+                    File::create(path).await.map_err(|e| e.to_string())?,
+                )),
+                None => Box::new(BufWriter::new(io::stdout())),
+            };
+
+            let count = Profile::export_all(&instance, &args.filter, out)
+                .await
+                .map_err(|e| e.to_string())?;
+            eprintln!("exported {count} profiles");
+        }
+        "import" => {
+            let args = TransferArgs::parse(args, "--input")?;
+            let input: Box<dyn AsyncBufRead + Unpin + Send> = match &args.file {
+                Some(path) => Box::new(BufReader::new(
+                    File::open(path).await.map_err(|e| e.to_string())?,
+                )),
+                None => Box::new(BufReader::new(io::stdin())),
+            };
+            let mode = if args.replace {
+                ImportMode::Replace
+            } else {
+                ImportMode::Merge
+            };
+
+            let report = Profile::import_all(&instance, &args.filter, mode, args.dry_run, input)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            for warning in report.warnings.iter() {
+                eprintln!("warning: {warning}");
+            }
+
+            eprintln!(
+                "{}created {}, updated {}, skipped {} removed",
+                if args.dry_run { "(dry run) " } else { "" },
+                report.created,
+                report.updated,
+                report.skipped
+            );
+        }
+        "migrate-template" => {
+            let dry_run = match args.collect::<Vec<_>>().as_slice() {
+                [] => false,
+                [flag] if flag == "--dry-run" => true,
+                _ => return Err(USAGE.to_string()),
+            };
+
+            let count = Profile::migrate_template(&instance, dry_run)
+                .await
+                .map_err(|e| e.to_string())?;
+            eprintln!(
+                "{}migrated {count} profiles",
+                if dry_run { "(dry run) " } else { "" }
+            );
+        }
+        _ => return Err(USAGE.to_string()),
+    }
+
+    Ok(())
+}
 
 #[cfg(feature = "core")]
 #[tokio::main]
-async fn main() {
-    let path = PathBuf::from(std::env::var("CONFIG").expect("env CONFIG not set"));
-    let instance = ProfileInstance::load(&path);
-    let port = instance.config.port;
-    let app = axum::Router::new().nest("/api/profile/v1", Router::get(instance));
-
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}",))
-        .await
-        .unwrap();
-    axum::serve(listener, app).await.unwrap();
+async fn main() -> ExitCode {
+    match start().await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+async fn start() -> Result<ExitCode, StartupError> {
+    let path = PathBuf::from(std::env::var("CONFIG").map_err(|_| StartupError::NoConfigPath)?);
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+
+    if command.as_deref() == Some("--check-config") {
+        MasterConfig::read(&path)?.validate()?;
+        eprintln!("config is valid");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let config = MasterConfig::read_or_create(&path)?;
+    config.validate()?;
+    init_logging(&config.log);
+    let instance = ProfileInstance::with_config(&path, config).await?;
+
+    if let Some(command) = command {
+        return Ok(match run_command(instance, &command, args).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{e}");
+                ExitCode::FAILURE
+            }
+        });
+    }
+
+    instance.spawn_purge();
+    instance.spawn_reload();
+    instance.spawn_profile_count();
+
+    if instance.live().config.watch.enabled {
+        instance.spawn_cache_invalidation();
+        instance.spawn_watch();
+    }
+
+    let config = instance.live().config.clone();
+    let address = config
+        .bind_address()
+        .map_err(|e| StartupError::Invalid(vec![FieldError::new("server.bind", e)]))?;
+    let app = match config.server.base_path.as_str() {
+        "" => Router::get(instance),
+        base_path => axum::Router::new().nest(base_path, Router::get(instance)),
+    };
+    let (signal, drained) = shutdown(Duration::from_secs(config.server.drain_timeout));
+    let bind_fail = |source| StartupError::Bind {
+        address: address.to_string(),
+        source,
+    };
+
+    let res = match &address {
+        BindAddress::Tcp(socket) => {
+            #[cfg(feature = "tls")]
+            if let Some(tls) = &config.server.tls {
+                let listener = TlsListener::bind(*socket, tls).await?;
+                let app = app.into_make_service_with_connect_info::<TlsPeer>();
+                let server = axum::serve(listener, app).with_graceful_shutdown(signal);
+                return Ok(exit_code(serve(server.into_future(), drained).await));
+            }
+
+            let listener = TcpListener::bind(socket).await.map_err(bind_fail)?;
+            let server = axum::serve(listener, app).with_graceful_shutdown(signal);
+            serve(server.into_future(), drained).await
+        }
+        #[cfg(unix)]
+        BindAddress::Unix(path) => {
+            // A socket left behind by an earlier run would make binding fail.
+            if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                fs::remove_file(path).map_err(bind_fail)?;
+            }
+
+            let listener = UnixListener::bind(path).map_err(bind_fail)?;
+            let server = axum::serve(listener, app).with_graceful_shutdown(signal);
+            let res = serve(server.into_future(), drained).await;
+            let _ = fs::remove_file(path);
+            res
+        }
+    };
+
+    Ok(exit_code(res))
+}
+
+#[cfg(feature = "core")]
+fn exit_code(res: io::Result<()>) -> ExitCode {
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!(error = %e, "server failed");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// A future resolving on SIGTERM or SIGINT, for `with_graceful_shutdown`, and one resolving
+/// `drain_timeout` after it.
+#[cfg(feature = "core")]
+fn shutdown(
+    drain_timeout: Duration,
+) -> (
+    impl Future<Output = ()> + Send + 'static,
+    impl Future<Output = ()>,
+) {
+    let (draining, drain) = oneshot::channel();
+
+    let signal = async move {
+        shutdown_signal().await;
+        info!("shutting down, draining in-flight requests");
+        let _ = draining.send(());
+    };
+    let drained = async move {
+        match drain.await {
+            Ok(()) => tokio::time::sleep(drain_timeout).await,
+            Err(_) => std::future::pending().await,
+        }
+    };
+
+    (signal, drained)
+}
+
+/// Runs `server` until it has drained or the drain timeout elapsed.
+#[cfg(feature = "core")]
+async fn serve(
+    server: impl Future<Output = io::Result<()>>,
+    drained: impl Future<Output = ()>,
+) -> io::Result<()> {
+    tokio::select! {
+        res = server => res,
+        _ = drained => {
+            warn!("drain timeout elapsed, dropping remaining requests");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
 }
 
 #[cfg(not(feature = "core"))]
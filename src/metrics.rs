@@ -0,0 +1,310 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use mongodb::{
+    bson::{doc, Bson},
+    event::{command::CommandEvent, EventHandler},
+};
+use prometheus::{
+    histogram_opts, opts, Encoder, Gauge, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    Registry, TextEncoder,
+};
+
+use crate::{instance::ProfileInstance, router::Router, ProfileServiceFunctions};
+
+/// Time between counts of the profiles in MongoDB for the `profiles` gauge.
+const PROFILE_COUNT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Prometheus metrics of an instance, exposed at `/metrics`.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub requests: IntCounterVec,
+    pub request_duration: HistogramVec,
+    pub storage_duration: HistogramVec,
+    pub storage_failures: IntCounterVec,
+    pub services_duration: HistogramVec,
+    pub services_failures: IntCounterVec,
+    pub profiles: IntGauge,
+    pub cache_hits: IntCounter,
+    pub cache_misses: IntCounter,
+    pub cache_hit_ratio: Gauge,
+    pub cache_entries: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("profile".to_string()), None).unwrap();
+
+        let requests = IntCounterVec::new(
+            opts!(
+                "http_requests_total",
+                "Requests handled by route and status"
+            ),
+            &["route", "status"],
+        )
+        .unwrap();
+        let request_duration = HistogramVec::new(
+            histogram_opts!("http_request_duration_seconds", "Request latency by route"),
+            &["route"],
+        )
+        .unwrap();
+        let storage_duration = HistogramVec::new(
+            histogram_opts!(
+                "storage_operation_duration_seconds",
+                "MongoDB command latency by command"
+            ),
+            &["command"],
+        )
+        .unwrap();
+        let storage_failures = IntCounterVec::new(
+            opts!(
+                "storage_failures_total",
+                "Failed MongoDB commands by command"
+            ),
+            &["command"],
+        )
+        .unwrap();
+        let services_duration = HistogramVec::new(
+            histogram_opts!(
+                "services_call_duration_seconds",
+                "atom-services call latency by call"
+            ),
+            &["call"],
+        )
+        .unwrap();
+        let services_failures = IntCounterVec::new(
+            opts!(
+                "services_call_failures_total",
+                "Failed atom-services calls by call"
+            ),
+            &["call"],
+        )
+        .unwrap();
+        let profiles =
+            IntGauge::new("profiles", "Profiles not removed, counted every minute").unwrap();
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry
+            .register(Box::new(request_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(storage_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(storage_failures.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(services_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(services_failures.clone()))
+            .unwrap();
+        let cache_hits =
+            IntCounter::new("cache_hits_total", "Profile reads served from the cache").unwrap();
+        let cache_misses = IntCounter::new(
+            "cache_misses_total",
+            "Profile reads the cache had to pass to MongoDB",
+        )
+        .unwrap();
+        let cache_hit_ratio = Gauge::new(
+            "cache_hit_ratio",
+            "Share of cached profile reads served from the cache, as of the last scrape",
+        )
+        .unwrap();
+        let cache_entries =
+            IntGauge::new("cache_entries", "Profiles cached, as of the last scrape").unwrap();
+
+        registry.register(Box::new(profiles.clone())).unwrap();
+        registry.register(Box::new(cache_hits.clone())).unwrap();
+        registry.register(Box::new(cache_misses.clone())).unwrap();
+        registry
+            .register(Box::new(cache_hit_ratio.clone()))
+            .unwrap();
+        registry.register(Box::new(cache_entries.clone())).unwrap();
+
+        Self {
+            registry,
+            requests,
+            request_duration,
+            storage_duration,
+            storage_failures,
+            services_duration,
+            services_failures,
+            profiles,
+            cache_hits,
+            cache_misses,
+            cache_hit_ratio,
+            cache_entries,
+        }
+    }
+
+    /// Registers an additional collector, such as ones of an embedding microservice.
+    pub fn register(&self, collector: Box<dyn prometheus::core::Collector>) {
+        if let Err(e) = self.registry.register(collector) {
+            tracing::warn!(error = %e, "failed to register metrics collector");
+        }
+    }
+
+    /// All metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Handler recording the latency of every MongoDB command.
+    pub(crate) fn command_events(&self) -> EventHandler<CommandEvent> {
+        let metrics = self.clone();
+
+        EventHandler::callback(move |event| match event {
+            CommandEvent::Succeeded(event) => metrics
+                .storage_duration
+                .with_label_values(&[&event.command_name])
+                .observe(event.duration.as_secs_f64()),
+            CommandEvent::Failed(event) => {
+                metrics
+                    .storage_duration
+                    .with_label_values(&[&event.command_name])
+                    .observe(event.duration.as_secs_f64());
+                metrics
+                    .storage_failures
+                    .with_label_values(&[&event.command_name])
+                    .inc();
+            }
+            _ => {}
+        })
+    }
+
+    fn observe_call(&self, call: &str, start: Instant, status: u16) {
+        self.services_duration
+            .with_label_values(&[call])
+            .observe(start.elapsed().as_secs_f64());
+
+        if status >= 500 {
+            self.services_failures.with_label_values(&[call]).inc();
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Middleware counting requests and their latency per route.
+pub(crate) async fn track(
+    State(instance): State<ProfileInstance>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    instance
+        .metrics
+        .request_duration
+        .with_label_values(&[&route])
+        .observe(start.elapsed().as_secs_f64());
+    instance
+        .metrics
+        .requests
+        .with_label_values(&[&route, response.status().as_str()])
+        .inc();
+
+    response
+}
+
+impl ProfileInstance {
+    /// Spawns the task keeping the `profiles` gauge up to date, so scrapes don't query MongoDB.
+    pub fn spawn_profile_count(&self) -> tokio::task::JoinHandle<()> {
+        let instance = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PROFILE_COUNT_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                match instance
+                    .profiles_doc
+                    .count_documents(doc! { "meta.deleted": Bson::Null })
+                    .await
+                {
+                    Ok(count) => instance.metrics.profiles.set(count as i64),
+                    Err(e) => tracing::warn!(error = %e, "failed to count profiles"),
+                }
+            }
+        })
+    }
+}
+
+impl Router {
+    pub async fn metrics(State(instance): State<ProfileInstance>) -> impl IntoResponse {
+        let metrics = &instance.metrics;
+        let (hits, misses) = (metrics.cache_hits.get(), metrics.cache_misses.get());
+
+        if hits + misses != 0 {
+            metrics
+                .cache_hit_ratio
+                .set(hits as f64 / (hits + misses) as f64);
+        }
+
+        metrics.cache_entries.set(instance.cache.len() as i64);
+
+        (
+            StatusCode::OK,
+            [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+            instance.metrics.render(),
+        )
+    }
+}
+
+/// Services client recording the latency and failures of each call.
+#[derive(Clone)]
+pub(crate) struct MeteredServices {
+    pub inner: Box<dyn ProfileServiceFunctions>,
+    pub metrics: Metrics,
+}
+
+#[async_trait]
+impl ProfileServiceFunctions for MeteredServices {
+    async fn exists(
+        &self,
+        req: atom_services::schema::ExistsReq,
+    ) -> (u16, atom_services::schema::ExistsRes) {
+        let start = Instant::now();
+        let res = self.inner.exists(req).await;
+        self.metrics.observe_call("exists", start, res.0);
+        res
+    }
+
+    async fn show(
+        &self,
+        req: atom_services::schema::ShowReq,
+    ) -> (u16, atom_services::schema::ShowRes) {
+        let start = Instant::now();
+        let res = self.inner.show(req).await;
+        self.metrics.observe_call("show", start, res.0);
+        res
+    }
+
+    async fn ready(&self) -> Result<(), String> {
+        self.inner.ready().await
+    }
+}
@@ -1,10 +1,27 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use atom_services::schema::{ExistsReq, ExistsRes};
-use mongodb::bson::{doc, Bson, Document};
+use futures::future::try_join_all;
+use mongodb::{
+    bson::{doc, Bson, Document},
+    error::{ErrorKind, WriteError, WriteFailure},
+    results::UpdateResult,
+};
 use serde::{Deserialize, Serialize};
+use tracing::{instrument, Level};
 
-use crate::instance::ProfileInstance;
+use crate::{
+    caller::Caller,
+    defaults::Defaults,
+    grants::Grants,
+    instance::ProfileInstance,
+    registry::SchemaRegistry,
+    schema::{OverlayLayer, OverlayValue, ProfileExport, ProfileMeta, Selector},
+};
 
 macro_rules! opt_unwrap {
     ($x: expr) => {
@@ -23,6 +40,28 @@ macro_rules! not_found {
     };
 }
 
+macro_rules! already_exists {
+    () => {
+        Err(mongodb::error::Error::custom(
+            "profile already exists".to_string(),
+        ))
+    };
+}
+
+macro_rules! removed {
+    () => {
+        Err(mongodb::error::Error::custom(
+            "profile is removed".to_string(),
+        ))
+    };
+}
+
+macro_rules! denied {
+    () => {
+        Err(mongodb::error::Error::custom("access denied".to_string()))
+    };
+}
+
 macro_rules! no_service {
     () => {
         Err(mongodb::error::Error::custom(
@@ -34,13 +73,87 @@ macro_rules! no_service {
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Profile {
     #[serde(rename = "_id")]
-    id: u64,
-    bucket: BTreeMap<String, String>,
-    services: BTreeMap<String, BTreeMap<String, String>>,
+    pub(crate) id: u64,
+    #[serde(default)]
+    pub(crate) bucket: BTreeMap<String, String>,
+    #[serde(default)]
+    pub(crate) services: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default)]
+    pub(crate) meta: ProfileMeta,
 }
 
 impl Profile {
-    fn encode(s: &str) -> String {
+    pub(crate) fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+
+    /// Records the modification time and author into a `$set` document.
+    fn touch(m_set: &mut Document, service: Option<&str>) {
+        m_set.insert("meta.modified", Bson::Int64(Self::now() as i64));
+        m_set.insert("meta.modified_by", service);
+    }
+
+    /// Matches profile `id` unless it is soft deleted.
+    pub(crate) fn filter(id: u64) -> Document {
+        doc! { "_id": Bson::Int64(id as i64), "meta.deleted": Bson::Null }
+    }
+
+    /// Whether profile `id` is soft deleted.
+    async fn is_removed(
+        instance: &ProfileInstance,
+        id: u64,
+    ) -> Result<bool, mongodb::error::Error> {
+        Ok(instance
+            .profiles_doc
+            .count_documents(
+                doc! { "_id": Bson::Int64(id as i64), "meta.deleted": { "$ne": Bson::Null } },
+            )
+            .limit(1)
+            .await?
+            != 0)
+    }
+
+    /// Updates profile `id` unless it is soft deleted, creating it if `upsert` is set.
+    ///
+    /// The filter doesn't consist of `_id` alone, so MongoDB won't retry an upsert racing another
+    /// first write to the same id. A duplicate key is retried once here instead, and only
+    /// reported as a removed profile if it persists because of one.
+    async fn update_live(
+        instance: &ProfileInstance,
+        id: u64,
+        update: Document,
+        upsert: bool,
+    ) -> Result<UpdateResult, mongodb::error::Error> {
+        let attempt = || {
+            instance
+                .profiles
+                .update_one(Self::filter(id), update.clone())
+                .upsert(upsert)
+        };
+
+        let res = match attempt().await {
+            Err(e) if Self::is_duplicate(&e) => attempt().await,
+            res => res,
+        };
+        instance.cache.invalidate(id);
+
+        match res {
+            Err(e) if Self::is_duplicate(&e) && Self::is_removed(instance, id).await? => removed!(),
+            res => res,
+        }
+    }
+
+    pub(crate) fn is_duplicate(e: &mongodb::error::Error) -> bool {
+        matches!(
+            e.kind.as_ref(),
+            ErrorKind::Write(WriteFailure::WriteError(WriteError { code: 11000, .. }))
+        )
+    }
+
+    pub(crate) fn encode(s: &str) -> String {
         let mut out = String::new();
 
         for c in s.chars() {
@@ -54,7 +167,26 @@ impl Profile {
         out
     }
 
-    fn decode(s: &str) -> String {
+    fn decode_map(map: BTreeMap<String, String>) -> BTreeMap<String, String> {
+        map.into_iter()
+            .map(|(k, v)| (Self::decode(&k), v))
+            .collect()
+    }
+
+    pub(crate) fn into_export(self) -> ProfileExport {
+        ProfileExport {
+            id: self.id,
+            meta: self.meta,
+            bucket: Self::decode_map(self.bucket),
+            services: self
+                .services
+                .into_iter()
+                .map(|(service, entries)| (service, Self::decode_map(entries)))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn decode(s: &str) -> String {
         let mut out = String::new();
         let mut op = false;
 
@@ -94,112 +226,276 @@ impl Profile {
             }
         }
 
-        if instance
-            .profiles
-            .update_one(
-                doc! { "_id": Bson::Int64(id as i64) },
-                doc! { "$set": m_set , "$unset": m_unset},
-            )
-            .await?
-            .matched_count
-            == 0
+        Self::touch(&mut m_set, None);
+
+        Self::update_live(
+            instance,
+            id,
+            doc! {
+                "$setOnInsert": Self::on_insert(instance, &[&m_set, &m_unset]),
+                "$set": m_set,
+                "$unset": m_unset,
+            },
+            true,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Projects the namespace at `path` down to what `select` can match.
+    pub(crate) fn projection(select: &Selector, path: &str) -> Document {
+        // Prefixes can only be matched after decoding, and projection paths can't hold empty
+        // components or ones starting with `$`, as keys starting with `$` or `.` encode to, so
+        // these fall back to the whole namespace.
+        if select.all
+            || !select.prefixes.is_empty()
+            || select
+                .entries
+                .iter()
+                .any(|k| k.is_empty() || Self::encode(k).starts_with('$'))
+        {
+            return doc! { path: 1 };
+        }
+
+        if select.entries.is_empty() {
+            return doc! { "_id": 1 };
+        }
+
+        select
+            .entries
+            .iter()
+            .map(|k| (format!("{path}.{}", Self::encode(k)), Bson::Int32(1)))
+            .collect()
+    }
+
+    pub(crate) fn select_entries(
+        namespace: Option<&Document>,
+        select: &Selector,
+    ) -> BTreeMap<String, String> {
+        namespace
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, v)| {
+                let k = Self::decode(k);
+                select
+                    .matches(&k)
+                    .then(|| (k, v.as_str().unwrap_or_default().to_string()))
+            })
+            .collect()
+    }
+
+    pub(crate) async fn check_service(
+        instance: &ProfileInstance,
+        service: &str,
+    ) -> Result<(), mongodb::error::Error> {
+        let live = instance.live();
+        let ttl = Duration::from_secs(live.config.cache.services_ttl);
+
+        if live
+            .known_services
+            .lock()
+            .unwrap()
+            .get(service)
+            .is_some_and(|found| found.elapsed() < ttl)
         {
+            return Ok(());
+        }
+
+        let (_, res) = live
+            .services
+            .exists(ExistsReq {
+                id: service.to_string(),
+            })
+            .await;
+
+        match res {
+            ExistsRes::Exists { value: false } => no_service!(),
+            ExistsRes::Exists { value: true } => {
+                live.known_services
+                    .lock()
+                    .unwrap()
+                    .insert(service.to_string(), Instant::now());
+                Ok(())
+            }
+            ExistsRes::Error { reason } => Err(mongodb::error::Error::custom(reason)),
+        }
+    }
+
+    /// Path of the layer within a profile, `None` for layers stored elsewhere.
+    fn layer_path(layer: &OverlayLayer) -> Option<String> {
+        match layer {
+            OverlayLayer::Bucket => Some("bucket".to_string()),
+            OverlayLayer::Service { name } => Some(format!("services.{name}")),
+            OverlayLayer::Defaults { .. } => None,
+        }
+    }
+
+    fn layer_entries<'a>(profile: &'a Document, layer: &OverlayLayer) -> Option<&'a Document> {
+        match layer {
+            OverlayLayer::Bucket => profile.get_document("bucket").ok(),
+            OverlayLayer::Service { name } => profile
+                .get_document("services")
+                .ok()
+                .and_then(|services| services.get_document(name).ok()),
+            OverlayLayer::Defaults { .. } => None,
+        }
+    }
+
+    /// Reads profile `id` whole through the cache, or only `projection` of it if the cache is
+    /// disabled or bypassed with `select.fresh`.
+    async fn find(
+        instance: &ProfileInstance,
+        id: u64,
+        select: &Selector,
+        projection: Document,
+    ) -> Result<Option<Arc<Document>>, mongodb::error::Error> {
+        let live = instance.live();
+        let cache = &live.config.cache;
+
+        if cache.size == 0 || select.fresh {
+            return Ok(instance
+                .profiles_doc
+                .find_one(Self::filter(id))
+                .projection(projection)
+                .await?
+                .map(Arc::new));
+        }
+
+        if let Some(profile) = instance.cache.get(id, Duration::from_secs(cache.ttl)) {
+            instance.metrics.cache_hits.inc();
+            return Ok(Some(profile));
+        }
+
+        instance.metrics.cache_misses.inc();
+        let generation = instance.cache.generation();
+        let profile = instance
+            .profiles_doc
+            .find_one(Self::filter(id))
+            .await?
+            .map(Arc::new);
+
+        if let Some(profile) = &profile {
             instance
-                .profiles
-                .insert_one(Profile {
-                    id,
-                    bucket: entries.into_iter().collect(),
-                    services: BTreeMap::new(),
-                })
-                .await?;
+                .cache
+                .put(id, profile.clone(), generation, cache.size);
         }
 
-        Ok(())
+        Ok(profile)
     }
 
     async fn get_int(
         instance: &ProfileInstance,
         id: u64,
-        entries: Vec<String>,
+        select: &Selector,
     ) -> Result<BTreeMap<String, String>, mongodb::error::Error> {
-        let mut projection = Document::new();
+        let profile = opt_unwrap!(
+            Self::find(instance, id, select, Self::projection(select, "bucket")).await?
+        );
 
-        for k in entries.into_iter() {
-            projection.insert(Self::encode(&k), 1);
-        }
+        let mut entries = Self::select_entries(profile.get_document("bucket").ok(), select);
+        Self::apply_virtual_template(instance, &OverlayLayer::Bucket, select, &mut entries);
 
-        Ok(opt_unwrap!(
-            instance
-                .profiles_doc
-                .find_one(doc! { "_id": Bson::Int64(id as i64)})
-                .projection(doc! { "bucket": projection })
-                .await?
-        )
-        .get_document("bucket")
-        .cloned()
-        .unwrap_or_default()
-        .into_iter()
-        .map(|(k, v)| (Self::decode(&k), v.as_str().unwrap().to_string()))
-        .collect())
+        Ok(entries)
     }
 
     async fn remove_int(instance: &ProfileInstance, id: u64) -> Result<(), mongodb::error::Error> {
-        if instance
+        let mut m_set = doc! { "meta.deleted": Bson::Int64(Self::now() as i64) };
+        Self::touch(&mut m_set, None);
+
+        let res = instance
             .profiles
-            .delete_one(doc! { "_id": Bson::Int64(id as i64) })
-            .await?
-            .deleted_count
-            == 0
-        {
+            .update_one(Self::filter(id), doc! { "$set": m_set })
+            .await;
+        instance.cache.invalidate(id);
+
+        if res?.matched_count == 0 {
+            return not_found!();
+        }
+
+        Ok(())
+    }
+
+    /// Oldest removal time that is still within the retention period.
+    fn retention_cutoff(instance: &ProfileInstance) -> i64 {
+        Self::now().saturating_sub(instance.live().config.removal.retention * 1000) as i64
+    }
+
+    async fn restore_int(instance: &ProfileInstance, id: u64) -> Result<(), mongodb::error::Error> {
+        let mut m_set = Document::new();
+        Self::touch(&mut m_set, None);
+
+        let res = instance
+            .profiles
+            .update_one(
+                doc! {
+                    "_id": Bson::Int64(id as i64),
+                    "meta.deleted": { "$gte": Self::retention_cutoff(instance) },
+                },
+                doc! { "$set": m_set, "$unset": { "meta.deleted": "" } },
+            )
+            .await;
+        instance.cache.invalidate(id);
+
+        if res?.matched_count == 0 {
             return not_found!();
         }
 
         Ok(())
     }
 
+    async fn purge_int(instance: &ProfileInstance) -> Result<u64, mongodb::error::Error> {
+        // Only removed profiles are purged, and those are never cached.
+        Ok(instance
+            .profiles
+            .delete_many(doc! { "meta.deleted": { "$lt": Self::retention_cutoff(instance) } })
+            .await?
+            .deleted_count)
+    }
+
     async fn set_service_int(
         instance: &ProfileInstance,
         id: u64,
         service: &str,
         entries: Vec<(String, String)>,
     ) -> Result<(), mongodb::error::Error> {
-        let (_, res) = instance
-            .services
-            .exists(ExistsReq {
-                id: service.to_string(),
-            })
-            .await;
-
-        match res {
-            ExistsRes::Exists { value: false } => return no_service!(),
-            ExistsRes::Exists { value: true } => {}
-            ExistsRes::Error { reason } => return Err(mongodb::error::Error::custom(reason)),
-        }
+        Self::check_service(instance, service).await?;
 
         let mut m_set = Document::new();
         let mut m_unset = Document::new();
 
         for (k, v) in entries.iter() {
             if v.is_empty() {
-                m_unset.insert(format!("services.{service}.{}", Self::encode(k)), v.to_string());
+                m_unset.insert(
+                    format!("services.{service}.{}", Self::encode(k)),
+                    v.to_string(),
+                );
             } else {
-                m_set.insert(format!("services.{service}.{}", Self::encode(k)), v.to_string());
+                m_set.insert(
+                    format!("services.{service}.{}", Self::encode(k)),
+                    v.to_string(),
+                );
             }
         }
 
-        if instance
-            .profiles
-            .update_one(
-                doc! { "_id": Bson::Int64(id as i64) },
-                doc! { "$set": m_set, "$unset": m_unset},
-            )
-            .await?
-            .matched_count
-            == 1
-        {
-            Ok(())
-        } else {
+        Self::touch(&mut m_set, Some(service));
+        let upsert = instance.live().config.auto_create.allows(service);
+
+        let res = Self::update_live(
+            instance,
+            id,
+            doc! {
+                "$setOnInsert": Self::on_insert(instance, &[&m_set, &m_unset]),
+                "$set": m_set,
+                "$unset": m_unset,
+            },
+            upsert,
+        )
+        .await?;
+
+        if res.matched_count == 0 && !upsert {
             not_found!()
+        } else {
+            Ok(())
         }
     }
 
@@ -207,43 +503,27 @@ impl Profile {
         instance: &ProfileInstance,
         id: u64,
         service: &str,
-        entries: Vec<String>,
+        select: &Selector,
     ) -> Result<BTreeMap<String, String>, mongodb::error::Error> {
-        let (_, res) = instance
-            .services
-            .exists(ExistsReq {
-                id: service.to_string(),
-            })
-            .await;
-
-        match res {
-            ExistsRes::Exists { value: false } => return no_service!(),
-            ExistsRes::Exists { value: true } => {}
-            ExistsRes::Error { reason } => return Err(mongodb::error::Error::custom(reason)),
-        }
+        Self::check_service(instance, service).await?;
+
+        let profile = opt_unwrap!(
+            Self::find(
+                instance,
+                id,
+                select,
+                Self::projection(select, &format!("services.{service}"))
+            )
+            .await?
+        );
 
-        let mut projection = Document::new();
+        let layer = OverlayLayer::Service {
+            name: service.to_string(),
+        };
+        let mut entries = Self::select_entries(Self::layer_entries(&profile, &layer), select);
+        Self::apply_virtual_template(instance, &layer, select, &mut entries);
 
-        for k in entries.into_iter() {
-            projection.insert(Self::encode(&k), 1);
-        }
-
-        Ok(opt_unwrap!(
-            instance
-                .profiles_doc
-                .find_one(doc! { "_id": Bson::Int64(id as i64)})
-                .projection(doc! { "services": doc!{ service: projection}})
-                .await?
-        )
-        .get_document("services")
-        .cloned()
-        .unwrap_or_default()
-        .get_document(service)
-        .cloned()
-        .unwrap_or_default()
-        .into_iter()
-        .map(|(k, v)| (Self::decode(&k), v.as_str().unwrap().to_string()))
-        .collect())
+        Ok(entries)
     }
 
     async fn remove_service_int(
@@ -251,108 +531,376 @@ impl Profile {
         id: u64,
         service: &str,
     ) -> Result<(), mongodb::error::Error> {
-        let (_, res) = instance
-            .services
-            .exists(ExistsReq {
-                id: service.to_string(),
-            })
-            .await;
+        Self::check_service(instance, service).await?;
 
-        match res {
-            ExistsRes::Exists { value: false } => return no_service!(),
-            ExistsRes::Exists { value: true } => {}
-            ExistsRes::Error { reason } => return Err(mongodb::error::Error::custom(reason)),
-        }
+        let mut m_set = Document::new();
+        Self::touch(&mut m_set, Some(service));
 
-        if instance
+        let res = instance
             .profiles
             .update_one(
-                doc! { "_id": Bson::Int64(id as i64) },
-                doc! { "$unset": doc!{ "services": service}},
+                Self::filter(id),
+                doc! { "$set": m_set, "$unset": doc!{ format!("services.{service}"): ""}},
             )
-            .await?
-            .matched_count
-            == 0
-        {
+            .await;
+        instance.cache.invalidate(id);
+
+        if res?.matched_count == 0 {
             not_found!()
         } else {
             Ok(())
         }
     }
 
+    async fn create_int(instance: &ProfileInstance, id: u64) -> Result<(), mongodb::error::Error> {
+        let now = Self::now();
+        let mut profile = Self::from_template(instance);
+        profile.id = id;
+        profile.meta.created = now;
+        profile.meta.modified = now;
+
+        let res = instance.profiles.insert_one(profile).await;
+        instance.cache.invalidate(id);
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) if Self::is_duplicate(&e) && Self::is_removed(instance, id).await? => removed!(),
+            Err(e) if Self::is_duplicate(&e) => already_exists!(),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn exists_int(
+        instance: &ProfileInstance,
+        id: u64,
+    ) -> Result<bool, mongodb::error::Error> {
+        Ok(instance
+            .profiles_doc
+            .count_documents(Self::filter(id))
+            .limit(1)
+            .await?
+            != 0)
+    }
+
+    async fn get_meta_int(
+        instance: &ProfileInstance,
+        id: u64,
+    ) -> Result<ProfileMeta, mongodb::error::Error> {
+        Ok(opt_unwrap!(
+            instance
+                .profiles
+                .find_one(Self::filter(id))
+                .projection(doc! { "meta": 1 })
+                .await?
+        )
+        .meta)
+    }
+
+    async fn export_int(
+        instance: &ProfileInstance,
+        id: u64,
+    ) -> Result<ProfileExport, mongodb::error::Error> {
+        Ok(opt_unwrap!(instance.profiles.find_one(Self::filter(id)).await?).into_export())
+    }
+
+    /// Stacks `entries` from `source` on top of the values resolved so far.
+    fn overlay(
+        values: &mut BTreeMap<String, OverlayValue>,
+        source: OverlayLayer,
+        entries: BTreeMap<String, String>,
+    ) {
+        for (k, value) in entries.into_iter() {
+            let shadowed = values.remove(&k).map(Box::new);
+            values.insert(
+                k,
+                OverlayValue {
+                    value,
+                    source: source.clone(),
+                    shadowed,
+                },
+            );
+        }
+    }
+
+    /// Resolves `layers`, ordered from highest to lowest priority, in a single read.
     async fn get_overlay_int(
         instance: &ProfileInstance,
+        caller: &Caller,
         id: u64,
-        service: &str,
-        entries: Vec<String>,
-    ) -> Result<BTreeMap<String, String>, mongodb::error::Error> {
-        let mut service_entries =
-            Self::get_service_int(instance, id, service, entries.clone()).await?;
+        layers: &[OverlayLayer],
+        select: &Selector,
+    ) -> Result<BTreeMap<String, OverlayValue>, mongodb::error::Error> {
+        let mut unique = Vec::with_capacity(layers.len());
+
+        for layer in layers.iter() {
+            if !unique.contains(layer) {
+                unique.push(layer.clone());
+            }
+        }
 
-        let remaining = entries
-            .into_iter()
-            .filter(|s| !service_entries.contains_key(s))
+        // Keys of each service layer the caller may read, `None` for unrestricted.
+        let readable = try_join_all(unique.iter().filter_map(|layer| match layer {
+            OverlayLayer::Service { name } => Some(async move {
+                Self::check_service(instance, name).await?;
+                Ok::<_, mongodb::error::Error>((
+                    name.clone(),
+                    Grants::readable(instance, caller, name).await?,
+                ))
+            }),
+            OverlayLayer::Bucket | OverlayLayer::Defaults { .. } => None,
+        }))
+        .await?
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+
+        let mut projection = doc! { "_id": 1 };
+
+        for layer in unique.iter() {
+            if let Some(path) = Self::layer_path(layer) {
+                projection.extend(Self::projection(select, &path));
+            }
+        }
+
+        let profile = opt_unwrap!(Self::find(instance, id, select, projection).await?);
+
+        // Defaults of every service in the chain sit below all other layers, in chain order.
+        let default_layers = unique
+            .iter()
+            .filter_map(|layer| match layer {
+                OverlayLayer::Service { name } => {
+                    Some(OverlayLayer::Defaults { name: name.clone() })
+                }
+                OverlayLayer::Bucket | OverlayLayer::Defaults { .. } => None,
+            })
+            .filter(|layer| !unique.contains(layer))
+            .collect::<Vec<_>>();
+        unique.extend(default_layers);
+
+        let services = unique
+            .iter()
+            .filter_map(|layer| match layer {
+                OverlayLayer::Defaults { name } => Some(name.as_str()),
+                OverlayLayer::Bucket | OverlayLayer::Service { .. } => None,
+            })
             .collect::<Vec<_>>();
-        let global_entries = Self::get_int(instance, id, remaining).await?;
-        service_entries.extend(global_entries);
+        let defaults = Defaults::get_many(instance, &services).await?;
+
+        let mut values = BTreeMap::new();
+
+        for layer in unique.into_iter().rev() {
+            let entries = match &layer {
+                OverlayLayer::Defaults { name } => Self::select_entries(defaults.get(name), select),
+                layer => {
+                    let mut entries =
+                        Self::select_entries(Self::layer_entries(&profile, layer), select);
+                    Self::apply_virtual_template(instance, layer, select, &mut entries);
+
+                    if let OverlayLayer::Service { name } = layer {
+                        if let Some(Some(keys)) = readable.get(name) {
+                            entries.retain(|k, _| keys.contains(k));
+                        }
+                    }
+
+                    entries
+                }
+            };
+            Self::overlay(&mut values, layer, entries);
+        }
 
-        Ok(service_entries)
+        Ok(values)
     }
 }
 
 impl Profile {
+    #[instrument(skip_all, fields(id = id), err(level = Level::WARN))]
     pub async fn show(
         instance: &ProfileInstance,
         id: u64,
-        entries: Vec<String>,
+        select: Selector,
     ) -> Result<BTreeMap<String, String>, mongodb::error::Error> {
-        Self::get_int(instance, id, entries).await
+        Self::get_int(instance, id, &select).await
     }
 
+    #[instrument(skip_all, fields(id = id, service = %service, caller = ?caller), err(level = Level::WARN))]
     pub async fn show_service(
         instance: &ProfileInstance,
+        caller: &Caller,
         id: u64,
         service: &str,
-        entries: Vec<String>,
+        select: Selector,
     ) -> Result<BTreeMap<String, String>, mongodb::error::Error> {
-        Self::get_service_int(instance, id, service, entries).await
+        let readable = Grants::readable(instance, caller, service).await?;
+
+        if readable.as_ref().is_some_and(BTreeSet::is_empty) {
+            return denied!();
+        }
+
+        let mut entries = Self::get_service_int(instance, id, service, &select).await?;
+
+        if let Some(readable) = readable {
+            entries.retain(|k, _| readable.contains(k));
+        }
+
+        Ok(entries)
     }
 
+    #[instrument(skip_all, fields(id = id, caller = ?caller), err(level = Level::WARN))]
     pub async fn show_overlay(
         instance: &ProfileInstance,
+        caller: &Caller,
         id: u64,
-        service: &str,
-        entries: Vec<String>,
-    ) -> Result<BTreeMap<String, String>, mongodb::error::Error> {
-        Self::get_overlay_int(instance, id, service, entries).await
+        layers: &[OverlayLayer],
+        select: Selector,
+    ) -> Result<(BTreeMap<String, String>, BTreeSet<String>), mongodb::error::Error> {
+        let mut values = BTreeMap::new();
+        let mut defaulted = BTreeSet::new();
+
+        for (k, v) in Self::get_overlay_int(instance, caller, id, layers, &select)
+            .await?
+            .into_iter()
+        {
+            if let OverlayLayer::Defaults { .. } = v.source {
+                defaulted.insert(k.clone());
+            }
+
+            values.insert(k, v.value);
+        }
+
+        Ok((values, defaulted))
     }
 
+    #[instrument(skip_all, fields(id = id, caller = ?caller), err(level = Level::WARN))]
+    pub async fn show_overlay_provenance(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        id: u64,
+        layers: &[OverlayLayer],
+        select: Selector,
+    ) -> Result<BTreeMap<String, OverlayValue>, mongodb::error::Error> {
+        Self::get_overlay_int(instance, caller, id, layers, &select).await
+    }
+
+    #[instrument(skip_all, fields(id = id), err(level = Level::WARN))]
     pub async fn set(
         instance: &ProfileInstance,
         id: u64,
         entries: Vec<(String, String)>,
-    ) -> Result<(), mongodb::error::Error> {
-        Self::set_int(instance, id, entries).await
+    ) -> Result<Vec<String>, mongodb::error::Error> {
+        let warnings = SchemaRegistry::validate(instance, None, &entries).await?;
+        Self::set_int(instance, id, entries).await?;
+        Ok(warnings)
     }
 
+    #[instrument(skip_all, fields(id = id, service = %service, caller = ?caller), err(level = Level::WARN))]
     pub async fn set_service(
         instance: &ProfileInstance,
+        caller: &Caller,
         id: u64,
         service: &str,
         entries: Vec<(String, String)>,
-    ) -> Result<(), mongodb::error::Error> {
-        Self::set_service_int(instance, id, service, entries).await
+    ) -> Result<Vec<String>, mongodb::error::Error> {
+        if let Some(writable) = Grants::writable(instance, caller, service).await? {
+            if let Some((k, _)) = entries.iter().find(|(k, _)| !writable.contains(k)) {
+                return Err(mongodb::error::Error::custom(format!(
+                    "access denied to {k}"
+                )));
+            }
+        }
+
+        let warnings = SchemaRegistry::validate(instance, Some(service), &entries).await?;
+        Self::set_service_int(instance, id, service, entries).await?;
+        Ok(warnings)
     }
 
-    pub async fn remove(instance: &ProfileInstance, id: u64) -> Result<(), mongodb::error::Error> {
+    #[instrument(skip_all, fields(id = id), err(level = Level::WARN))]
+    pub async fn create(instance: &ProfileInstance, id: u64) -> Result<(), mongodb::error::Error> {
+        Self::create_int(instance, id).await
+    }
+
+    #[instrument(skip_all, fields(id = id), err(level = Level::WARN))]
+    pub async fn exists(
+        instance: &ProfileInstance,
+        id: u64,
+    ) -> Result<bool, mongodb::error::Error> {
+        Self::exists_int(instance, id).await
+    }
+
+    #[instrument(skip_all, fields(id = id), err(level = Level::WARN))]
+    pub async fn show_meta(
+        instance: &ProfileInstance,
+        id: u64,
+    ) -> Result<ProfileMeta, mongodb::error::Error> {
+        Self::get_meta_int(instance, id).await
+    }
+
+    /// Exports the profile with only the service keys the caller may read.
+    #[instrument(skip_all, fields(id = id, caller = ?caller), err(level = Level::WARN))]
+    pub async fn export(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        id: u64,
+    ) -> Result<ProfileExport, mongodb::error::Error> {
+        let mut export = Self::export_int(instance, id).await?;
+        let mut services = BTreeMap::new();
+
+        for (service, mut entries) in std::mem::take(&mut export.services) {
+            if let Some(readable) = Grants::readable(instance, caller, &service).await? {
+                entries.retain(|k, _| readable.contains(k));
+            }
+
+            if !entries.is_empty() {
+                services.insert(service, entries);
+            }
+        }
+
+        export.services = services;
+        Ok(export)
+    }
+
+    #[instrument(skip_all, fields(id = id, caller = ?caller), err(level = Level::WARN))]
+    pub async fn remove(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        id: u64,
+    ) -> Result<(), mongodb::error::Error> {
+        if !caller.may_remove_profiles(instance) {
+            return denied!();
+        }
+
         Self::remove_int(instance, id).await
     }
 
+    #[instrument(skip_all, fields(id = id, caller = ?caller), err(level = Level::WARN))]
+    pub async fn restore(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        id: u64,
+    ) -> Result<(), mongodb::error::Error> {
+        if !caller.may_remove_profiles(instance) {
+            return denied!();
+        }
+
+        Self::restore_int(instance, id).await
+    }
+
+    /// Permanently deletes profiles removed longer ago than the retention period.
+    #[instrument(skip_all, err(level = Level::WARN))]
+    pub async fn purge(instance: &ProfileInstance) -> Result<u64, mongodb::error::Error> {
+        Self::purge_int(instance).await
+    }
+
+    /// Removes a whole namespace, which only its owner may do, grants don't extend to it.
+    #[instrument(skip_all, fields(id = id, service = %service, caller = ?caller), err(level = Level::WARN))]
     pub async fn remove_service(
         instance: &ProfileInstance,
+        caller: &Caller,
         id: u64,
         service: &str,
     ) -> Result<(), mongodb::error::Error> {
+        if !caller.owns(service) {
+            return denied!();
+        }
+
         Self::remove_service_int(instance, id, service).await
     }
 }
@@ -0,0 +1,389 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use mongodb::bson::{self, doc};
+use regex::Regex;
+use tracing::{instrument, Level};
+
+use crate::{
+    instance::ProfileInstance,
+    schema::{KeySchema, NamespaceSchema, ValueType},
+    Caller, Profile, ValidationMode,
+};
+
+macro_rules! denied {
+    () => {
+        Err(mongodb::error::Error::custom("access denied".to_string()))
+    };
+}
+
+/// Namespace schemas with their patterns compiled, read again after `cache.schema-ttl` and
+/// dropped when a schema is set through this instance or the config is reloaded.
+#[derive(Default)]
+pub struct SchemaCache {
+    entries: Mutex<HashMap<String, (Arc<CompiledSchema>, Instant)>>,
+    /// Bumped by every invalidation, so schemas read before one aren't cached after it.
+    generation: AtomicU64,
+}
+
+struct CompiledSchema {
+    schema: NamespaceSchema,
+    patterns: BTreeMap<String, Regex>,
+}
+
+impl SchemaCache {
+    /// The cached schema, unless it is older than `ttl`.
+    fn get(&self, id: &str, ttl: Duration) -> Option<Arc<CompiledSchema>> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(id) {
+            Some((schema, stored)) if stored.elapsed() < ttl => Some(schema.clone()),
+            Some(_) => {
+                entries.remove(id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    fn put(&self, id: String, schema: Arc<CompiledSchema>, generation: u64) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if self.generation() == generation {
+            entries.insert(id, (schema, Instant::now()));
+        }
+    }
+
+    fn invalidate(&self, id: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.remove(id);
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        entries.clear();
+    }
+}
+
+/// Per namespace schemas checked when setting values.
+///
+/// Stored as one document per namespace, `_id` being `bucket` or `service:{name}`.
+pub struct SchemaRegistry;
+
+impl SchemaRegistry {
+    fn id(service: Option<&str>) -> String {
+        match service {
+            Some(service) => format!("service:{service}"),
+            None => "bucket".to_string(),
+        }
+    }
+
+    /// Describes why `value` violates `schema`, if it does, `pattern` being its compiled pattern.
+    fn check(schema: &KeySchema, pattern: Option<&Regex>, value: &str) -> Option<String> {
+        let type_error = match schema.kind {
+            None | Some(ValueType::String) => None,
+            Some(ValueType::Integer) => value.parse::<i64>().is_err().then_some("an integer"),
+            Some(ValueType::Number) => {
+                (!value.parse::<f64>().is_ok_and(f64::is_finite)).then_some("a number")
+            }
+            Some(ValueType::Boolean) => {
+                (value != "true" && value != "false").then_some("a boolean")
+            }
+        };
+
+        if let Some(expected) = type_error {
+            return Some(format!("must be {expected}"));
+        }
+
+        if let Some(allowed) = &schema.allowed {
+            if !allowed.iter().any(|v| v == value) {
+                return Some(format!("must be one of {}", allowed.join(", ")));
+            }
+        }
+
+        if let Some(source) = &schema.pattern {
+            if !pattern.is_some_and(|re| re.is_match(value)) {
+                return Some(format!("must match {source}"));
+            }
+        }
+
+        if schema.minimum.is_some() || schema.maximum.is_some() {
+            let Ok(number) = value.parse::<f64>() else {
+                return Some("must be a number".to_string());
+            };
+
+            if schema.minimum.is_some_and(|min| number < min) {
+                return Some(format!("must be at least {}", schema.minimum.unwrap()));
+            }
+
+            if schema.maximum.is_some_and(|max| number > max) {
+                return Some(format!("must be at most {}", schema.maximum.unwrap()));
+            }
+        }
+
+        None
+    }
+
+    async fn set_int(
+        instance: &ProfileInstance,
+        service: Option<&str>,
+        schema: NamespaceSchema,
+    ) -> Result<(), mongodb::error::Error> {
+        if let Some(service) = service {
+            Profile::check_service(instance, service).await?;
+        }
+
+        for (key, schema) in schema.keys.iter() {
+            if let Some(pattern) = &schema.pattern {
+                if let Err(e) = Regex::new(pattern) {
+                    return Err(mongodb::error::Error::custom(format!(
+                        "invalid pattern for {key}: {e}"
+                    )));
+                }
+            }
+        }
+
+        let keys = schema
+            .keys
+            .into_iter()
+            .map(|(k, v)| (Profile::encode(&k), v))
+            .collect::<BTreeMap<_, _>>();
+        let keys =
+            bson::to_document(&keys).map_err(|e| mongodb::error::Error::custom(e.to_string()))?;
+
+        instance
+            .schemas
+            .replace_one(
+                doc! { "_id": Self::id(service) },
+                doc! { "keys": keys, "additional": schema.additional },
+            )
+            .upsert(true)
+            .await?;
+
+        instance.schema_cache.invalidate(&Self::id(service));
+        Ok(())
+    }
+
+    async fn get_int(
+        instance: &ProfileInstance,
+        service: Option<&str>,
+    ) -> Result<NamespaceSchema, mongodb::error::Error> {
+        let Some(stored) = instance
+            .schemas
+            .find_one(doc! { "_id": Self::id(service) })
+            .await?
+        else {
+            return Ok(NamespaceSchema::default());
+        };
+
+        let keys: BTreeMap<String, KeySchema> =
+            bson::from_document(stored.get_document("keys").cloned().unwrap_or_default())
+                .map_err(|e| mongodb::error::Error::custom(e.to_string()))?;
+
+        Ok(NamespaceSchema {
+            keys: keys
+                .into_iter()
+                .map(|(k, v)| (Profile::decode(&k), v))
+                .collect(),
+            additional: stored.get_bool("additional").unwrap_or(true),
+        })
+    }
+
+    /// The namespace schema with its patterns compiled, read through the schema cache.
+    async fn compiled(
+        instance: &ProfileInstance,
+        service: Option<&str>,
+    ) -> Result<Arc<CompiledSchema>, mongodb::error::Error> {
+        let id = Self::id(service);
+        let ttl = Duration::from_secs(instance.live().config.cache.schema_ttl);
+
+        if let Some(compiled) = instance.schema_cache.get(&id, ttl) {
+            return Ok(compiled);
+        }
+
+        let generation = instance.schema_cache.generation();
+        let schema = Self::get_int(instance, service).await?;
+        let patterns = schema
+            .keys
+            .iter()
+            .filter_map(|(k, v)| Some((k.clone(), Regex::new(v.pattern.as_ref()?).ok()?)))
+            .collect();
+
+        let compiled = Arc::new(CompiledSchema { schema, patterns });
+        instance.schema_cache.put(id, compiled.clone(), generation);
+        Ok(compiled)
+    }
+
+    /// Checks `entries` against the namespace schema, returning the violations as warnings
+    /// in warn-only mode or failing on the first of them in strict mode.
+    pub(crate) async fn validate(
+        instance: &ProfileInstance,
+        service: Option<&str>,
+        entries: &[(String, String)],
+    ) -> Result<Vec<String>, mongodb::error::Error> {
+        let compiled = Self::compiled(instance, service).await?;
+        let schema = &compiled.schema;
+        let mut violations = Vec::new();
+
+        // Empty values remove keys, which is always allowed.
+        for (key, value) in entries.iter().filter(|(_, v)| !v.is_empty()) {
+            let violation = match schema.keys.get(key) {
+                Some(key_schema) => Self::check(key_schema, compiled.patterns.get(key), value),
+                None if !schema.additional => Some("is not a known key".to_string()),
+                None => None,
+            };
+
+            if let Some(violation) = violation {
+                violations.push(format!("{key} {violation}"));
+            }
+        }
+
+        match (instance.live().config.validation.mode, violations.first()) {
+            (ValidationMode::Strict, Some(violation)) => {
+                Err(mongodb::error::Error::custom(violation.clone()))
+            }
+            _ => Ok(violations),
+        }
+    }
+}
+
+impl SchemaRegistry {
+    /// Sets the schema of a service namespace, which only the service itself and admins may do,
+    /// or of the bucket, which only admins may do.
+    #[instrument(skip_all, fields(service = ?service, caller = ?caller), err(level = Level::WARN))]
+    pub async fn set(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        service: Option<&str>,
+        schema: NamespaceSchema,
+    ) -> Result<(), mongodb::error::Error> {
+        if !service.is_some_and(|service| caller.owns(service)) && !caller.is_admin(instance) {
+            return denied!();
+        }
+
+        Self::set_int(instance, service, schema).await
+    }
+
+    #[instrument(skip_all, fields(service = ?service), err(level = Level::WARN))]
+    pub async fn show(
+        instance: &ProfileInstance,
+        service: Option<&str>,
+    ) -> Result<NamespaceSchema, mongodb::error::Error> {
+        Self::get_int(instance, service).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(schema: &KeySchema, value: &str) -> Option<String> {
+        let pattern = schema.pattern.as_deref().and_then(|p| Regex::new(p).ok());
+        SchemaRegistry::check(schema, pattern.as_ref(), value)
+    }
+
+    fn of_type(kind: ValueType) -> KeySchema {
+        KeySchema {
+            kind: Some(kind),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_types() {
+        assert_eq!(check(&of_type(ValueType::String), "anything"), None);
+        assert_eq!(check(&of_type(ValueType::Integer), "-12"), None);
+        assert_eq!(
+            check(&of_type(ValueType::Integer), "1.5").as_deref(),
+            Some("must be an integer")
+        );
+        assert_eq!(check(&of_type(ValueType::Number), "1.5e3"), None);
+        assert_eq!(
+            check(&of_type(ValueType::Number), "inf").as_deref(),
+            Some("must be a number")
+        );
+        assert_eq!(check(&of_type(ValueType::Boolean), "false"), None);
+        assert_eq!(
+            check(&of_type(ValueType::Boolean), "yes").as_deref(),
+            Some("must be a boolean")
+        );
+    }
+
+    #[test]
+    fn check_allowed() {
+        let schema = KeySchema {
+            allowed: Some(vec!["light".to_string(), "dark".to_string()]),
+            ..Default::default()
+        };
+
+        assert_eq!(check(&schema, "dark"), None);
+        assert_eq!(
+            check(&schema, "blue").as_deref(),
+            Some("must be one of light, dark")
+        );
+    }
+
+    #[test]
+    fn check_pattern() {
+        let schema = KeySchema {
+            pattern: Some("^[a-z]{2}-[A-Z]{2}$".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(check(&schema, "en-GB"), None);
+        assert_eq!(
+            check(&schema, "english").as_deref(),
+            Some("must match ^[a-z]{2}-[A-Z]{2}$")
+        );
+        // A pattern that doesn't compile matches nothing.
+        assert!(SchemaRegistry::check(&schema, None, "en-GB").is_some());
+    }
+
+    #[test]
+    fn check_bounds() {
+        let schema = KeySchema {
+            minimum: Some(1.0),
+            maximum: Some(10.0),
+            ..Default::default()
+        };
+
+        assert_eq!(check(&schema, "1"), None);
+        assert_eq!(check(&schema, "10"), None);
+        assert_eq!(check(&schema, "0.5").as_deref(), Some("must be at least 1"));
+        assert_eq!(check(&schema, "11").as_deref(), Some("must be at most 10"));
+        assert_eq!(check(&schema, "ten").as_deref(), Some("must be a number"));
+    }
+
+    #[test]
+    fn schema_cache_expires() {
+        let cache = SchemaCache::default();
+        let compiled = Arc::new(CompiledSchema {
+            schema: NamespaceSchema::default(),
+            patterns: BTreeMap::new(),
+        });
+
+        cache.put("bucket".to_string(), compiled.clone(), cache.generation());
+        assert!(cache.get("bucket", Duration::from_secs(60)).is_some());
+        assert!(cache.get("bucket", Duration::ZERO).is_none());
+        // Expired entries are dropped.
+        assert!(cache.get("bucket", Duration::from_secs(60)).is_none());
+
+        // Schemas read before an invalidation aren't cached.
+        let generation = cache.generation();
+        cache.invalidate("bucket");
+        cache.put("bucket".to_string(), compiled, generation);
+        assert!(cache.get("bucket", Duration::from_secs(60)).is_none());
+    }
+}
@@ -1,6 +1,9 @@
-use axum::routing::post;
+use axum::{
+    middleware,
+    routing::{get, post},
+};
 
-use crate::instance::ProfileInstance;
+use crate::{instance::ProfileInstance, limits, metrics, trace};
 
 pub struct InternalRouter;
 pub struct Router;
@@ -8,13 +11,38 @@ pub struct Router;
 impl Router {
     pub fn get(instance: ProfileInstance) -> axum::Router {
         axum::Router::new()
+            .route("/admin/set-defaults", post(Router::set_defaults))
+            .route("/admin/set-schema", post(Router::set_schema))
+            .route("/admin/show-defaults", post(Router::show_defaults))
+            .route("/admin/show-schema", post(Router::show_schema))
+            .route("/create", post(Router::create))
+            .route("/exists", post(Router::exists))
+            .route("/export", post(Router::export))
+            .route("/grant", post(Router::grant))
             .route("/remove", post(Router::remove))
             .route("/remove-service", post(Router::remove_service))
+            .route("/restore", post(Router::restore))
+            .route("/revoke", post(Router::revoke))
             .route("/set", post(Router::set))
             .route("/set-service", post(Router::set_service))
             .route("/show", post(Router::show))
+            .route("/show-grants", post(Router::show_grants))
+            .route("/show-meta", post(Router::show_meta))
             .route("/show-overlay", post(Router::show_overlay))
             .route("/show-service", post(Router::show_service))
+            // Probes and scrapes are added after so they are never limited.
+            .route_layer(middleware::from_fn_with_state(
+                instance.clone(),
+                limits::limit,
+            ))
+            .route("/health", get(Router::health))
+            .route("/metrics", get(Router::metrics))
+            .route("/ready", get(Router::ready))
+            .layer(middleware::from_fn_with_state(
+                instance.clone(),
+                metrics::track,
+            ))
+            .layer(middleware::from_fn(trace::trace))
             .with_state(instance)
     }
 }
@@ -0,0 +1,69 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Profile,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateReq {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CreateRes {
+    #[serde(rename = "created")]
+    Created,
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl CreateRes {
+    pub fn success(_: ()) -> Self {
+        Self::Created
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            CreateRes::Created => StatusCode::OK,
+            CreateRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn create(instance: &ProfileInstance, payload: CreateReq) -> CreateRes {
+        Profile::create(instance, payload.id)
+            .await
+            .map(CreateRes::success)
+            .unwrap_or_else(CreateRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn create(
+        State(instance): State<ProfileInstance>,
+        Json(payload): Json<CreateReq>,
+    ) -> (StatusCode, Json<CreateRes>) {
+        let res = InternalRouter::create(&instance, payload).await;
+        (res.status(), Json(res))
+    }
+}
@@ -0,0 +1,69 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Profile,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct ExistsReq {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ExistsRes {
+    #[serde(rename = "exists")]
+    Exists { value: bool },
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl ExistsRes {
+    pub fn success(value: bool) -> Self {
+        Self::Exists { value }
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ExistsRes::Exists { .. } => StatusCode::OK,
+            ExistsRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn exists(instance: &ProfileInstance, payload: ExistsReq) -> ExistsRes {
+        Profile::exists(instance, payload.id)
+            .await
+            .map(ExistsRes::success)
+            .unwrap_or_else(ExistsRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn exists(
+        State(instance): State<ProfileInstance>,
+        Json(payload): Json<ExistsReq>,
+    ) -> (StatusCode, Json<ExistsRes>) {
+        let res = InternalRouter::exists(&instance, payload).await;
+        (res.status(), Json(res))
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use super::ProfileMeta;
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Caller, Profile,
+};
+
+/// Everything stored about a profile, with keys in their original form.
+///
+/// This format is stable, fields may be added but are never renamed or removed:
+///
+/// ```json
+/// {
+///   "id": 1,
+///   "meta": { "created": 1700000000000, "modified": 1700000000000, "modified_by": "chat" },
+///   "bucket": { "locale": "en" },
+///   "services": { "chat": { "ui.theme": "dark" } }
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProfileExport {
+    pub id: u64,
+    #[serde(default)]
+    pub meta: ProfileMeta,
+    #[serde(default)]
+    pub bucket: BTreeMap<String, String>,
+    #[serde(default)]
+    pub services: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportReq {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ExportRes {
+    #[serde(rename = "export")]
+    Export { profile: ProfileExport },
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl ExportRes {
+    pub fn success(profile: ProfileExport) -> Self {
+        Self::Export { profile }
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ExportRes::Export { .. } => StatusCode::OK,
+            ExportRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn export(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        payload: ExportReq,
+    ) -> ExportRes {
+        Profile::export(instance, caller, payload.id)
+            .await
+            .map(ExportRes::success)
+            .unwrap_or_else(ExportRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn export(
+        State(instance): State<ProfileInstance>,
+        caller: Caller,
+        Json(payload): Json<ExportReq>,
+    ) -> (StatusCode, Json<ExportRes>) {
+        let res = InternalRouter::export(&instance, &caller, payload).await;
+        (res.status(), Json(res))
+    }
+}
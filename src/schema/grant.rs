@@ -0,0 +1,89 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Caller, Grants,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    #[serde(rename = "read")]
+    Read,
+    #[serde(rename = "read-write")]
+    ReadWrite,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GrantReq {
+    /// The namespace access is granted on, must be owned by the caller.
+    pub service: String,
+    pub grantee: String,
+    pub keys: Vec<String>,
+    pub access: Access,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum GrantRes {
+    #[serde(rename = "granted")]
+    Granted,
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl GrantRes {
+    pub fn success(_: ()) -> Self {
+        Self::Granted
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            GrantRes::Granted => StatusCode::OK,
+            GrantRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn grant(instance: &ProfileInstance, caller: &Caller, payload: GrantReq) -> GrantRes {
+        Grants::grant(
+            instance,
+            caller,
+            &payload.service,
+            &payload.grantee,
+            payload.keys,
+            payload.access,
+        )
+        .await
+        .map(GrantRes::success)
+        .unwrap_or_else(GrantRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn grant(
+        State(instance): State<ProfileInstance>,
+        caller: Caller,
+        Json(payload): Json<GrantReq>,
+    ) -> (StatusCode, Json<GrantRes>) {
+        let res = InternalRouter::grant(&instance, &caller, payload).await;
+        (res.status(), Json(res))
+    }
+}
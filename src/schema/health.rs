@@ -0,0 +1,38 @@
+#[cfg(feature = "core")]
+use axum::{http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::router::{InternalRouter, Router};
+
+/// Liveness, answered as long as the process serves requests.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum HealthRes {
+    #[serde(rename = "alive")]
+    Alive,
+}
+
+#[cfg(feature = "core")]
+impl HealthRes {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            HealthRes::Alive => StatusCode::OK,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    pub fn health() -> HealthRes {
+        HealthRes::Alive
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn health() -> (StatusCode, Json<HealthRes>) {
+        let res = InternalRouter::health();
+        (res.status(), Json(res))
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_default::DefaultFromSerde;
+use serde_inline_default::serde_inline_default;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    #[serde(rename = "string")]
+    String,
+    #[serde(rename = "integer")]
+    Integer,
+    #[serde(rename = "number")]
+    Number,
+    #[serde(rename = "boolean")]
+    Boolean,
+}
+
+/// Constraints on the value of a key, every constraint that is set must hold.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct KeySchema {
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ValueType>,
+    /// Exhaustive list of allowed values.
+    #[serde(default, rename = "enum", skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Vec<String>>,
+    /// Regular expression the value must contain a match of, anchor it to match the whole value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Inclusive lower bound, the value must be numeric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    /// Inclusive upper bound, the value must be numeric.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+}
+
+/// Schema of the global bucket or of a service namespace.
+#[serde_inline_default]
+#[derive(Serialize, Deserialize, DefaultFromSerde, Clone)]
+pub struct NamespaceSchema {
+    #[serde(default)]
+    pub keys: BTreeMap<String, KeySchema>,
+    /// Whether keys missing from `keys` may be set.
+    #[serde_inline_default(true)]
+    pub additional: bool,
+}
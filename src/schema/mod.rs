@@ -1,18 +1,66 @@
+mod create;
+pub use create::*;
+
+mod exists;
+pub use exists::*;
+
+mod grant;
+pub use grant::*;
+
+mod health;
+pub use health::*;
+
+mod ready;
+pub use ready::*;
+
+mod revoke;
+pub use revoke::*;
+
+mod restore;
+pub use restore::*;
+
+mod export;
+pub use export::*;
+
+mod selector;
+pub use selector::*;
+
 mod set;
 pub use set::*;
 
+mod set_defaults;
+pub use set_defaults::*;
+
+mod set_schema;
+pub use set_schema::*;
+
 mod set_service;
 pub use set_service::*;
 
 mod show;
 pub use show::*;
 
+mod show_defaults;
+pub use show_defaults::*;
+
+mod show_grants;
+pub use show_grants::*;
+
+mod show_meta;
+pub use show_meta::*;
+
+mod show_schema;
+pub use show_schema::*;
+
 mod show_service;
 pub use show_service::*;
 
 mod show_overlay;
 pub use show_overlay::*;
 
+mod key_schema;
+pub use key_schema::*;
+
 mod remove;
 pub use remove::*;
 
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "core")]
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+};
+
+/// Time each dependency has to answer before it is reported as unreachable.
+#[cfg(feature = "core")]
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DependencyStatus {
+    pub ok: bool,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Readiness, with the status of each dependency keyed by `storage` and `services`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ReadyRes {
+    #[serde(rename = "ready")]
+    Ready {
+        dependencies: BTreeMap<String, DependencyStatus>,
+    },
+    #[serde(rename = "not-ready")]
+    NotReady {
+        dependencies: BTreeMap<String, DependencyStatus>,
+    },
+}
+
+#[cfg(feature = "core")]
+impl DependencyStatus {
+    async fn check(check: impl Future<Output = Result<(), String>>) -> Self {
+        let start = Instant::now();
+        let res = tokio::time::timeout(CHECK_TIMEOUT, check)
+            .await
+            .unwrap_or_else(|_| Err("timed out".to_string()));
+
+        Self {
+            ok: res.is_ok(),
+            latency_ms: start.elapsed().as_millis() as u64,
+            reason: res.err(),
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl ReadyRes {
+    pub fn new(dependencies: BTreeMap<String, DependencyStatus>) -> Self {
+        if dependencies.values().all(|status| status.ok) {
+            Self::Ready { dependencies }
+        } else {
+            Self::NotReady { dependencies }
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ReadyRes::Ready { .. } => StatusCode::OK,
+            ReadyRes::NotReady { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn ready(instance: &ProfileInstance) -> ReadyRes {
+        let live = instance.live();
+        let (storage, services) = tokio::join!(
+            DependencyStatus::check(async {
+                instance.ping_storage().await.map_err(|e| e.to_string())
+            }),
+            DependencyStatus::check(live.services.ready()),
+        );
+
+        ReadyRes::new(BTreeMap::from([
+            ("storage".to_string(), storage),
+            ("services".to_string(), services),
+        ]))
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn ready(State(instance): State<ProfileInstance>) -> (StatusCode, Json<ReadyRes>) {
+        let res = InternalRouter::ready(&instance).await;
+        (res.status(), Json(res))
+    }
+}
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     instance::ProfileInstance,
     router::{InternalRouter, Router},
-    Profile,
+    Caller, Profile,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -48,8 +48,13 @@ impl RemoveRes {
 
 #[cfg(feature = "core")]
 impl InternalRouter {
-    pub async fn remove(instance: &ProfileInstance, payload: RemoveReq) -> RemoveRes {
-        Profile::remove(instance, payload.id)
+    #[tracing::instrument(skip_all)]
+    pub async fn remove(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        payload: RemoveReq,
+    ) -> RemoveRes {
+        Profile::remove(instance, caller, payload.id)
             .await
             .map(RemoveRes::success)
             .unwrap_or_else(RemoveRes::failure)
@@ -60,9 +65,10 @@ impl InternalRouter {
 impl Router {
     pub async fn remove(
         State(instance): State<ProfileInstance>,
+        caller: Caller,
         Json(payload): Json<RemoveReq>,
     ) -> (StatusCode, Json<RemoveRes>) {
-        let res = InternalRouter::remove(&instance, payload).await;
+        let res = InternalRouter::remove(&instance, &caller, payload).await;
         (res.status(), Json(res))
     }
 }
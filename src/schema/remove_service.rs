@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     instance::ProfileInstance,
     router::{InternalRouter, Router},
-    Profile,
+    Caller, Profile,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -49,11 +49,13 @@ impl RemoveServiceRes {
 
 #[cfg(feature = "core")]
 impl InternalRouter {
+    #[tracing::instrument(skip_all)]
     pub async fn remove_service(
         instance: &ProfileInstance,
+        caller: &Caller,
         payload: RemoveServiceReq,
     ) -> RemoveServiceRes {
-        Profile::remove_service(instance, payload.id, &payload.service)
+        Profile::remove_service(instance, caller, payload.id, &payload.service)
             .await
             .map(RemoveServiceRes::success)
             .unwrap_or_else(RemoveServiceRes::failure)
@@ -64,9 +66,10 @@ impl InternalRouter {
 impl Router {
     pub async fn remove_service(
         State(instance): State<ProfileInstance>,
+        caller: Caller,
         Json(payload): Json<RemoveServiceReq>,
     ) -> (StatusCode, Json<RemoveServiceRes>) {
-        let res = InternalRouter::remove_service(&instance, payload).await;
+        let res = InternalRouter::remove_service(&instance, &caller, payload).await;
         (res.status(), Json(res))
     }
 }
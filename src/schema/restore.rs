@@ -0,0 +1,74 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Caller, Profile,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct RestoreReq {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RestoreRes {
+    #[serde(rename = "restored")]
+    Restored,
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl RestoreRes {
+    pub fn success(_: ()) -> Self {
+        Self::Restored
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            RestoreRes::Restored => StatusCode::OK,
+            RestoreRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn restore(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        payload: RestoreReq,
+    ) -> RestoreRes {
+        Profile::restore(instance, caller, payload.id)
+            .await
+            .map(RestoreRes::success)
+            .unwrap_or_else(RestoreRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn restore(
+        State(instance): State<ProfileInstance>,
+        caller: Caller,
+        Json(payload): Json<RestoreReq>,
+    ) -> (StatusCode, Json<RestoreRes>) {
+        let res = InternalRouter::restore(&instance, &caller, payload).await;
+        (res.status(), Json(res))
+    }
+}
@@ -0,0 +1,83 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Caller, Grants,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct RevokeReq {
+    /// The namespace access is revoked on, must be owned by the caller.
+    pub service: String,
+    pub grantee: String,
+    pub keys: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RevokeRes {
+    #[serde(rename = "revoked")]
+    Revoked,
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl RevokeRes {
+    pub fn success(_: ()) -> Self {
+        Self::Revoked
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            RevokeRes::Revoked => StatusCode::OK,
+            RevokeRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn revoke(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        payload: RevokeReq,
+    ) -> RevokeRes {
+        Grants::revoke(
+            instance,
+            caller,
+            &payload.service,
+            &payload.grantee,
+            payload.keys,
+        )
+        .await
+        .map(RevokeRes::success)
+        .unwrap_or_else(RevokeRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn revoke(
+        State(instance): State<ProfileInstance>,
+        caller: Caller,
+        Json(payload): Json<RevokeReq>,
+    ) -> (StatusCode, Json<RevokeRes>) {
+        let res = InternalRouter::revoke(&instance, &caller, payload).await;
+        (res.status(), Json(res))
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+/// Keys to read from a namespace, a key is selected if any of the fields match it.
+///
+/// Nothing is selected when all fields are left empty.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Selector {
+    /// Select every key.
+    #[serde(default)]
+    pub all: bool,
+    /// Exact keys to select.
+    #[serde(default)]
+    pub entries: Vec<String>,
+    /// Select every key starting with one of these, e.g. `ui.`.
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    /// Read the profile from MongoDB even if it is cached.
+    #[serde(default)]
+    pub fresh: bool,
+}
+
+impl Selector {
+    pub fn all() -> Self {
+        Self {
+            all: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        self.all
+            || self.entries.iter().any(|k| k == key)
+            || self.prefixes.iter().any(|p| key.starts_with(p.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_selects_nothing() {
+        assert!(!Selector::default().matches("theme"));
+        assert!(!Selector::default().matches(""));
+    }
+
+    #[test]
+    fn all_selects_everything() {
+        assert!(Selector::all().matches("theme"));
+        assert!(Selector::all().matches("ui.font"));
+    }
+
+    #[test]
+    fn entries_match_exactly() {
+        let select = Selector {
+            entries: vec!["theme".to_string()],
+            ..Default::default()
+        };
+
+        assert!(select.matches("theme"));
+        assert!(!select.matches("theme.dark"));
+        assert!(!select.matches("them"));
+    }
+
+    #[test]
+    fn prefixes_and_entries_combine() {
+        let select = Selector {
+            entries: vec!["locale".to_string()],
+            prefixes: vec!["ui.".to_string()],
+            ..Default::default()
+        };
+
+        assert!(select.matches("locale"));
+        assert!(select.matches("ui.font"));
+        assert!(select.matches("ui."));
+        assert!(!select.matches("ui"));
+        assert!(!select.matches("theme"));
+    }
+
+    #[cfg(feature = "core")]
+    #[test]
+    fn projection_paths() {
+        use mongodb::bson::doc;
+
+        use crate::Profile;
+
+        let entries = |keys: &[&str]| Selector {
+            entries: keys.iter().map(|k| k.to_string()).collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Profile::projection(&entries(&["theme", "ui.font"]), "bucket"),
+            doc! { "bucket.theme": 1, "bucket.ui$pfont": 1 }
+        );
+        assert_eq!(
+            Profile::projection(&Selector::default(), "bucket"),
+            doc! { "_id": 1 }
+        );
+
+        for whole in [
+            Selector::all(),
+            Selector {
+                prefixes: vec!["ui.".to_string()],
+                ..Default::default()
+            },
+            entries(&["theme", "$x"]),
+            entries(&[".x"]),
+            entries(&[""]),
+        ] {
+            assert_eq!(Profile::projection(&whole, "bucket"), doc! { "bucket": 1 });
+        }
+    }
+}
@@ -31,15 +31,19 @@ pub struct SetReq {
 #[serde(tag = "type")]
 pub enum SetRes {
     #[serde(rename = "set")]
-    Set,
+    Set {
+        /// Schema violations let through in warn-only validation mode.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<String>,
+    },
     #[serde(rename = "error")]
     Error { reason: String },
 }
 
 #[cfg(feature = "core")]
 impl SetRes {
-    pub fn success(_: ()) -> Self {
-        Self::Set
+    pub fn success(warnings: Vec<String>) -> Self {
+        Self::Set { warnings }
     }
 
     pub fn failure(e: mongodb::error::Error) -> Self {
@@ -53,7 +57,7 @@ impl SetRes {
 
     pub fn status(&self) -> StatusCode {
         match self {
-            SetRes::Set => StatusCode::OK,
+            SetRes::Set { .. } => StatusCode::OK,
             SetRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -61,6 +65,7 @@ impl SetRes {
 
 #[cfg(feature = "core")]
 impl InternalRouter {
+    #[tracing::instrument(skip_all)]
     pub async fn set(instance: &ProfileInstance, payload: SetReq) -> SetRes {
         Profile::set(
             instance,
@@ -0,0 +1,96 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Caller, Defaults,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct SetDefaultsEntry {
+    pub key: String,
+    pub value: String,
+}
+
+impl SetDefaultsEntry {
+    pub fn into_tuple(self) -> (String, String) {
+        (self.key, self.value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SetDefaultsReq {
+    pub service: String,
+    pub entries: Vec<SetDefaultsEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SetDefaultsRes {
+    #[serde(rename = "set")]
+    Set,
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl SetDefaultsRes {
+    pub fn success(_: ()) -> Self {
+        Self::Set
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            SetDefaultsRes::Set => StatusCode::OK,
+            SetDefaultsRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn set_defaults(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        payload: SetDefaultsReq,
+    ) -> SetDefaultsRes {
+        Defaults::set(
+            instance,
+            caller,
+            &payload.service,
+            payload
+                .entries
+                .into_iter()
+                .map(SetDefaultsEntry::into_tuple)
+                .collect(),
+        )
+        .await
+        .map(SetDefaultsRes::success)
+        .unwrap_or_else(SetDefaultsRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn set_defaults(
+        State(instance): State<ProfileInstance>,
+        caller: Caller,
+        Json(payload): Json<SetDefaultsReq>,
+    ) -> (StatusCode, Json<SetDefaultsRes>) {
+        let res = InternalRouter::set_defaults(&instance, &caller, payload).await;
+        (res.status(), Json(res))
+    }
+}
@@ -0,0 +1,80 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use super::NamespaceSchema;
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Caller, SchemaRegistry,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct SetSchemaReq {
+    /// The service namespace, or the global bucket if `None`.
+    #[serde(default)]
+    pub service: Option<String>,
+    /// Replaces the current schema of the namespace.
+    pub schema: NamespaceSchema,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SetSchemaRes {
+    #[serde(rename = "set")]
+    Set,
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl SetSchemaRes {
+    pub fn success(_: ()) -> Self {
+        Self::Set
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            SetSchemaRes::Set => StatusCode::OK,
+            SetSchemaRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn set_schema(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        payload: SetSchemaReq,
+    ) -> SetSchemaRes {
+        SchemaRegistry::set(instance, caller, payload.service.as_deref(), payload.schema)
+            .await
+            .map(SetSchemaRes::success)
+            .unwrap_or_else(SetSchemaRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn set_schema(
+        State(instance): State<ProfileInstance>,
+        caller: Caller,
+        Json(payload): Json<SetSchemaReq>,
+    ) -> (StatusCode, Json<SetSchemaRes>) {
+        let res = InternalRouter::set_schema(&instance, &caller, payload).await;
+        (res.status(), Json(res))
+    }
+}
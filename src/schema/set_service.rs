@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     instance::ProfileInstance,
     router::{InternalRouter, Router},
-    Profile,
+    Caller, Profile,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -32,15 +32,19 @@ pub struct SetServiceReq {
 #[serde(tag = "type")]
 pub enum SetServiceRes {
     #[serde(rename = "set")]
-    Set,
+    Set {
+        /// Schema violations let through in warn-only validation mode.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<String>,
+    },
     #[serde(rename = "error")]
     Error { reason: String },
 }
 
 #[cfg(feature = "core")]
 impl SetServiceRes {
-    pub fn success(_: ()) -> Self {
-        Self::Set
+    pub fn success(warnings: Vec<String>) -> Self {
+        Self::Set { warnings }
     }
 
     pub fn failure(e: mongodb::error::Error) -> Self {
@@ -54,7 +58,7 @@ impl SetServiceRes {
 
     pub fn status(&self) -> StatusCode {
         match self {
-            SetServiceRes::Set => StatusCode::OK,
+            SetServiceRes::Set { .. } => StatusCode::OK,
             SetServiceRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -62,9 +66,15 @@ impl SetServiceRes {
 
 #[cfg(feature = "core")]
 impl InternalRouter {
-    pub async fn set_service(instance: &ProfileInstance, payload: SetServiceReq) -> SetServiceRes {
+    #[tracing::instrument(skip_all)]
+    pub async fn set_service(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        payload: SetServiceReq,
+    ) -> SetServiceRes {
         Profile::set_service(
             instance,
+            caller,
             payload.id,
             &payload.service,
             payload
@@ -83,9 +93,10 @@ impl InternalRouter {
 impl Router {
     pub async fn set_service(
         State(instance): State<ProfileInstance>,
+        caller: Caller,
         Json(payload): Json<SetServiceReq>,
     ) -> (StatusCode, Json<SetServiceRes>) {
-        let res = InternalRouter::set_service(&instance, payload).await;
+        let res = InternalRouter::set_service(&instance, &caller, payload).await;
         (res.status(), Json(res))
     }
 }
@@ -4,6 +4,8 @@ use std::collections::BTreeMap;
 use axum::{extract::State, http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
 
+use super::Selector;
+
 #[cfg(feature = "core")]
 use crate::{
     instance::ProfileInstance,
@@ -14,7 +16,8 @@ use crate::{
 #[derive(Serialize, Deserialize)]
 pub struct ShowReq {
     pub id: u64,
-    pub entries: Vec<String>,
+    #[serde(flatten)]
+    pub select: Selector,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -51,8 +54,9 @@ impl ShowRes {
 
 #[cfg(feature = "core")]
 impl InternalRouter {
+    #[tracing::instrument(skip_all)]
     pub async fn show(instance: &ProfileInstance, payload: ShowReq) -> ShowRes {
-        Profile::show(instance, payload.id, payload.entries)
+        Profile::show(instance, payload.id, payload.select)
             .await
             .map(ShowRes::success)
             .unwrap_or_else(ShowRes::failure)
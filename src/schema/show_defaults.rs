@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Defaults,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct ShowDefaultsReq {
+    pub service: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ShowDefaultsRes {
+    #[serde(rename = "show")]
+    Show { values: BTreeMap<String, String> },
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl ShowDefaultsRes {
+    pub fn success(values: BTreeMap<String, String>) -> Self {
+        Self::Show { values }
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ShowDefaultsRes::Show { .. } => StatusCode::OK,
+            ShowDefaultsRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn show_defaults(
+        instance: &ProfileInstance,
+        payload: ShowDefaultsReq,
+    ) -> ShowDefaultsRes {
+        Defaults::show(instance, &payload.service)
+            .await
+            .map(ShowDefaultsRes::success)
+            .unwrap_or_else(ShowDefaultsRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn show_defaults(
+        State(instance): State<ProfileInstance>,
+        Json(payload): Json<ShowDefaultsReq>,
+    ) -> (StatusCode, Json<ShowDefaultsRes>) {
+        let res = InternalRouter::show_defaults(&instance, payload).await;
+        (res.status(), Json(res))
+    }
+}
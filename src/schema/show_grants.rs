@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Caller, Grants,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Grant {
+    pub grantee: String,
+    pub read: BTreeSet<String>,
+    pub write: BTreeSet<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ShowGrantsReq {
+    pub service: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ShowGrantsRes {
+    #[serde(rename = "show")]
+    Show { grants: Vec<Grant> },
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl ShowGrantsRes {
+    pub fn success(grants: Vec<Grant>) -> Self {
+        Self::Show { grants }
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ShowGrantsRes::Show { .. } => StatusCode::OK,
+            ShowGrantsRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn show_grants(
+        instance: &ProfileInstance,
+        caller: &Caller,
+        payload: ShowGrantsReq,
+    ) -> ShowGrantsRes {
+        Grants::show(instance, caller, &payload.service)
+            .await
+            .map(ShowGrantsRes::success)
+            .unwrap_or_else(ShowGrantsRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn show_grants(
+        State(instance): State<ProfileInstance>,
+        caller: Caller,
+        Json(payload): Json<ShowGrantsReq>,
+    ) -> (StatusCode, Json<ShowGrantsRes>) {
+        let res = InternalRouter::show_grants(&instance, &caller, payload).await;
+        (res.status(), Json(res))
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    Profile,
+};
+
+/// Lifecycle metadata of a profile, timestamps are milliseconds since the Unix epoch.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ProfileMeta {
+    /// Zero for profiles created before metadata was recorded.
+    #[serde(default)]
+    pub created: u64,
+    #[serde(default)]
+    pub modified: u64,
+    /// Service that made the last modification, `None` for the global bucket.
+    #[serde(default)]
+    pub modified_by: Option<String>,
+    /// Removal time while the profile awaits purging, such profiles are hidden from reads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<u64>,
+    /// Version of the template last materialized into the profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ShowMetaReq {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ShowMetaRes {
+    #[serde(rename = "show")]
+    Show { meta: ProfileMeta },
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl ShowMetaRes {
+    pub fn success(meta: ProfileMeta) -> Self {
+        Self::Show { meta }
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ShowMetaRes::Show { .. } => StatusCode::OK,
+            ShowMetaRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn show_meta(instance: &ProfileInstance, payload: ShowMetaReq) -> ShowMetaRes {
+        Profile::show_meta(instance, payload.id)
+            .await
+            .map(ShowMetaRes::success)
+            .unwrap_or_else(ShowMetaRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn show_meta(
+        State(instance): State<ProfileInstance>,
+        Json(payload): Json<ShowMetaReq>,
+    ) -> (StatusCode, Json<ShowMetaRes>) {
+        let res = InternalRouter::show_meta(&instance, payload).await;
+        (res.status(), Json(res))
+    }
+}
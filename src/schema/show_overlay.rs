@@ -1,36 +1,96 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[cfg(feature = "core")]
 use axum::{extract::State, http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
 
+use super::Selector;
+
 #[cfg(feature = "core")]
 use crate::{
     instance::ProfileInstance,
     router::{InternalRouter, Router},
-    Profile,
+    Caller, Profile,
 };
 
 #[derive(Serialize, Deserialize)]
 pub struct ShowOverlayReq {
     pub id: u64,
-    pub service: String,
-    pub entries: Vec<String>,
+    /// Shorthand for the layers `[service, bucket]`, ignored if `layers` is set.
+    #[serde(default)]
+    pub service: Option<String>,
+    /// Layers ordered from highest to lowest priority.
+    #[serde(default)]
+    pub layers: Vec<OverlayLayer>,
+    #[serde(flatten)]
+    pub select: Selector,
+    /// Respond with where each value came from instead of the bare values.
+    #[serde(default)]
+    pub provenance: bool,
+}
+
+/// A namespace taking part in an overlay.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum OverlayLayer {
+    #[serde(rename = "service")]
+    Service { name: String },
+    #[serde(rename = "bucket")]
+    Bucket,
+    /// Defaults registered by a service, implicitly the lowest layers of every chain.
+    #[serde(rename = "defaults")]
+    Defaults { name: String },
+}
+
+/// A resolved overlay value along with the value it hides in the layer below, if any.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OverlayValue {
+    pub value: String,
+    pub source: OverlayLayer,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadowed: Option<Box<OverlayValue>>,
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum ShowOverlayRes {
     #[serde(rename = "show")]
-    Show { values: BTreeMap<String, String> },
+    Show {
+        values: BTreeMap<String, String>,
+        /// Keys whose value is a service default.
+        #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+        defaulted: BTreeSet<String>,
+    },
+    #[serde(rename = "provenance")]
+    Provenance {
+        values: BTreeMap<String, OverlayValue>,
+    },
     #[serde(rename = "error")]
     Error { reason: String },
 }
 
+impl ShowOverlayReq {
+    pub fn layers(&self) -> Vec<OverlayLayer> {
+        if !self.layers.is_empty() {
+            return self.layers.clone();
+        }
+
+        self.service
+            .iter()
+            .map(|name| OverlayLayer::Service { name: name.clone() })
+            .chain([OverlayLayer::Bucket])
+            .collect()
+    }
+}
+
 #[cfg(feature = "core")]
 impl ShowOverlayRes {
-    pub fn success(values: BTreeMap<String, String>) -> Self {
-        Self::Show { values }
+    pub fn success((values, defaulted): (BTreeMap<String, String>, BTreeSet<String>)) -> Self {
+        Self::Show { values, defaulted }
+    }
+
+    pub fn provenance(values: BTreeMap<String, OverlayValue>) -> Self {
+        Self::Provenance { values }
     }
 
     pub fn failure(e: mongodb::error::Error) -> Self {
@@ -44,7 +104,7 @@ impl ShowOverlayRes {
 
     pub fn status(&self) -> StatusCode {
         match self {
-            ShowOverlayRes::Show { .. } => StatusCode::OK,
+            ShowOverlayRes::Show { .. } | ShowOverlayRes::Provenance { .. } => StatusCode::OK,
             ShowOverlayRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -52,14 +112,25 @@ impl ShowOverlayRes {
 
 #[cfg(feature = "core")]
 impl InternalRouter {
+    #[tracing::instrument(skip_all)]
     pub async fn show_overlay(
         instance: &ProfileInstance,
+        caller: &Caller,
         payload: ShowOverlayReq,
     ) -> ShowOverlayRes {
-        Profile::show_overlay(instance, payload.id, &payload.service, payload.entries)
-            .await
-            .map(ShowOverlayRes::success)
-            .unwrap_or_else(ShowOverlayRes::failure)
+        let layers = payload.layers();
+
+        if payload.provenance {
+            Profile::show_overlay_provenance(instance, caller, payload.id, &layers, payload.select)
+                .await
+                .map(ShowOverlayRes::provenance)
+                .unwrap_or_else(ShowOverlayRes::failure)
+        } else {
+            Profile::show_overlay(instance, caller, payload.id, &layers, payload.select)
+                .await
+                .map(ShowOverlayRes::success)
+                .unwrap_or_else(ShowOverlayRes::failure)
+        }
     }
 }
 
@@ -67,9 +138,10 @@ impl InternalRouter {
 impl Router {
     pub async fn show_overlay(
         State(instance): State<ProfileInstance>,
+        caller: Caller,
         Json(payload): Json<ShowOverlayReq>,
     ) -> (StatusCode, Json<ShowOverlayRes>) {
-        let res = InternalRouter::show_overlay(&instance, payload).await;
+        let res = InternalRouter::show_overlay(&instance, &caller, payload).await;
         (res.status(), Json(res))
     }
 }
@@ -0,0 +1,73 @@
+#[cfg(feature = "core")]
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use super::NamespaceSchema;
+
+#[cfg(feature = "core")]
+use crate::{
+    instance::ProfileInstance,
+    router::{InternalRouter, Router},
+    SchemaRegistry,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct ShowSchemaReq {
+    /// The service namespace, or the global bucket if `None`.
+    #[serde(default)]
+    pub service: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ShowSchemaRes {
+    #[serde(rename = "show")]
+    Show { schema: NamespaceSchema },
+    #[serde(rename = "error")]
+    Error { reason: String },
+}
+
+#[cfg(feature = "core")]
+impl ShowSchemaRes {
+    pub fn success(schema: NamespaceSchema) -> Self {
+        Self::Show { schema }
+    }
+
+    pub fn failure(e: mongodb::error::Error) -> Self {
+        Self::Error {
+            reason: e
+                .get_custom::<String>()
+                .cloned()
+                .unwrap_or(e.kind.to_string()),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ShowSchemaRes::Show { .. } => StatusCode::OK,
+            ShowSchemaRes::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "core")]
+impl InternalRouter {
+    #[tracing::instrument(skip_all)]
+    pub async fn show_schema(instance: &ProfileInstance, payload: ShowSchemaReq) -> ShowSchemaRes {
+        SchemaRegistry::show(instance, payload.service.as_deref())
+            .await
+            .map(ShowSchemaRes::success)
+            .unwrap_or_else(ShowSchemaRes::failure)
+    }
+}
+
+#[cfg(feature = "core")]
+impl Router {
+    pub async fn show_schema(
+        State(instance): State<ProfileInstance>,
+        Json(payload): Json<ShowSchemaReq>,
+    ) -> (StatusCode, Json<ShowSchemaRes>) {
+        let res = InternalRouter::show_schema(&instance, payload).await;
+        (res.status(), Json(res))
+    }
+}
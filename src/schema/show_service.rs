@@ -4,18 +4,21 @@ use std::collections::BTreeMap;
 use axum::{extract::State, http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
 
+use super::Selector;
+
 #[cfg(feature = "core")]
 use crate::{
     instance::ProfileInstance,
     router::{InternalRouter, Router},
-    Profile,
+    Caller, Profile,
 };
 
 #[derive(Serialize, Deserialize)]
 pub struct ShowServiceReq {
     pub id: u64,
     pub service: String,
-    pub entries: Vec<String>,
+    #[serde(flatten)]
+    pub select: Selector,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -52,14 +55,22 @@ impl ShowServiceRes {
 
 #[cfg(feature = "core")]
 impl InternalRouter {
+    #[tracing::instrument(skip_all)]
     pub async fn show_service(
         instance: &ProfileInstance,
+        caller: &Caller,
         payload: ShowServiceReq,
     ) -> ShowServiceRes {
-        Profile::show_service(instance, payload.id, &payload.service, payload.entries)
-            .await
-            .map(ShowServiceRes::success)
-            .unwrap_or_else(ShowServiceRes::failure)
+        Profile::show_service(
+            instance,
+            caller,
+            payload.id,
+            &payload.service,
+            payload.select,
+        )
+        .await
+        .map(ShowServiceRes::success)
+        .unwrap_or_else(ShowServiceRes::failure)
     }
 }
 
@@ -67,9 +78,10 @@ impl InternalRouter {
 impl Router {
     pub async fn show_service(
         State(instance): State<ProfileInstance>,
+        caller: Caller,
         Json(payload): Json<ShowServiceReq>,
     ) -> (StatusCode, Json<ShowServiceRes>) {
-        let res = InternalRouter::show_service(&instance, payload).await;
+        let res = InternalRouter::show_service(&instance, &caller, payload).await;
         (res.status(), Json(res))
     }
 }
@@ -0,0 +1,98 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+/// Why the instance could not be started.
+#[derive(Debug)]
+pub enum StartupError {
+    /// The `CONFIG` environment variable is not set.
+    NoConfigPath,
+    /// The config file is missing and `strict` forbids creating it.
+    MissingConfig(PathBuf),
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        reason: String,
+    },
+    /// A `_FILE` environment variable points to a file that can't be read.
+    Secret {
+        name: String,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Invalid(Vec<FieldError>),
+    Storage(mongodb::error::Error),
+    /// Certificates or keys for serving TLS can't be loaded.
+    Tls(String),
+    Bind {
+        address: String,
+        source: io::Error,
+    },
+}
+
+/// A config field rejected by validation, `field` being its dotted path in the config file.
+#[derive(Debug)]
+pub struct FieldError {
+    pub field: String,
+    pub reason: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, reason: impl ToString) -> Self {
+        Self {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for StartupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoConfigPath => write!(f, "env CONFIG not set"),
+            Self::MissingConfig(path) => {
+                write!(f, "config file {} does not exist", path.display())
+            }
+            Self::Io { path, source } => write!(f, "failed to access {}: {source}", path.display()),
+            Self::Parse { path, reason } => write!(f, "bad config in {}: {reason}", path.display()),
+            Self::Secret { name, path, source } => {
+                write!(f, "failed to read {name} from {}: {source}", path.display())
+            }
+            Self::Invalid(errors) => {
+                write!(f, "invalid config:")?;
+
+                for error in errors {
+                    write!(f, "\n    {}: {}", error.field, error.reason)?;
+                }
+
+                Ok(())
+            }
+            Self::Storage(e) => write!(f, "failed to connect to MongoDB: {e}"),
+            Self::Tls(reason) => write!(f, "failed to set up TLS: {reason}"),
+            Self::Bind { address, source } => write!(f, "failed to bind {address}: {source}"),
+        }
+    }
+}
+
+impl Error for StartupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } | Self::Secret { source, .. } | Self::Bind { source, .. } => {
+                Some(source)
+            }
+            Self::Storage(e) => Some(e),
+            Self::NoConfigPath
+            | Self::MissingConfig(_)
+            | Self::Parse { .. }
+            | Self::Invalid(_)
+            | Self::Tls(_) => None,
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for StartupError {
+    fn from(value: mongodb::error::Error) -> Self {
+        Self::Storage(value)
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use mongodb::bson::{doc, Bson, Document};
+
+use crate::{
+    instance::ProfileInstance,
+    schema::{OverlayLayer, Selector},
+    Profile, TemplateMode,
+};
+
+impl Profile {
+    fn encode_map(entries: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (Self::encode(k), v.clone()))
+            .collect()
+    }
+
+    /// An empty profile with the template materialized into it, if configured to.
+    pub(crate) fn from_template(instance: &ProfileInstance) -> Profile {
+        let live = instance.live();
+        let template = &live.config.template;
+        let mut profile = Profile::default();
+
+        if template.mode == TemplateMode::Materialize {
+            profile.bucket = Self::encode_map(&template.bucket);
+            profile.services = template
+                .services
+                .iter()
+                .map(|(service, entries)| (service.clone(), Self::encode_map(entries)))
+                .collect();
+            profile.meta.template = Some(template.version);
+        }
+
+        profile
+    }
+
+    /// `$setOnInsert` for upserts, materializing the template around the paths in `written`.
+    pub(crate) fn on_insert(instance: &ProfileInstance, written: &[&Document]) -> Document {
+        let live = instance.live();
+        let template = &live.config.template;
+        let mut on_insert = doc! { "meta.created": Bson::Int64(Self::now() as i64) };
+
+        if template.mode != TemplateMode::Materialize {
+            return on_insert;
+        }
+
+        on_insert.insert("meta.template", Bson::Int64(template.version as i64));
+
+        let bucket = template
+            .bucket
+            .iter()
+            .map(|(k, v)| (format!("bucket.{}", Self::encode(k)), v));
+        let services = template.services.iter().flat_map(|(service, entries)| {
+            entries
+                .iter()
+                .map(move |(k, v)| (format!("services.{service}.{}", Self::encode(k)), v))
+        });
+
+        for (path, value) in bucket.chain(services) {
+            if !written.iter().any(|update| update.contains_key(&path)) {
+                on_insert.insert(path, value.clone());
+            }
+        }
+
+        on_insert
+    }
+
+    /// Fills selected keys missing from `entries` with template values in virtual mode.
+    pub(crate) fn apply_virtual_template(
+        instance: &ProfileInstance,
+        layer: &OverlayLayer,
+        select: &Selector,
+        entries: &mut BTreeMap<String, String>,
+    ) {
+        let live = instance.live();
+        let template = &live.config.template;
+
+        if template.mode != TemplateMode::Virtual {
+            return;
+        }
+
+        let values = match layer {
+            OverlayLayer::Bucket => Some(&template.bucket),
+            OverlayLayer::Service { name } => template.services.get(name),
+            OverlayLayer::Defaults { .. } => None,
+        };
+
+        for (k, v) in values.into_iter().flatten() {
+            if select.matches(k) && !entries.contains_key(k) {
+                entries.insert(k.clone(), v.clone());
+            }
+        }
+    }
+
+    async fn migrate_template_int(
+        instance: &ProfileInstance,
+        dry_run: bool,
+    ) -> Result<u64, mongodb::error::Error> {
+        let live = instance.live();
+        let template = &live.config.template;
+
+        if template.mode != TemplateMode::Materialize {
+            return Err(mongodb::error::Error::custom(
+                "template is not materialized".to_string(),
+            ));
+        }
+
+        let filter = doc! {
+            "meta.deleted": Bson::Null,
+            "meta.template": { "$not": { "$gte": Bson::Int64(template.version as i64) } },
+        };
+
+        if dry_run {
+            return instance.profiles_doc.count_documents(filter).await;
+        }
+
+        // Existing values take precedence over the template, `$literal` keeps encoded keys
+        // starting with `$` from being read as operators.
+        let merge = |path: String, entries: &BTreeMap<String, String>| {
+            let entries = entries
+                .iter()
+                .map(|(k, v)| (Self::encode(k), Bson::String(v.clone())))
+                .collect::<Document>();
+            (
+                path.clone(),
+                Bson::Document(doc! {
+                    "$mergeObjects": [ { "$literal": entries }, format!("${path}") ]
+                }),
+            )
+        };
+
+        let mut stage = template
+            .services
+            .iter()
+            .map(|(service, entries)| merge(format!("services.{service}"), entries))
+            .collect::<Document>();
+        let (path, bucket) = merge("bucket".to_string(), &template.bucket);
+        stage.insert(path, bucket);
+        stage.insert(
+            "meta.template",
+            doc! { "$literal": Bson::Int64(template.version as i64) },
+        );
+
+        let res = instance
+            .profiles_doc
+            .update_many(filter, vec![doc! { "$set": stage }])
+            .await;
+        instance.cache.clear();
+
+        Ok(res?.modified_count)
+    }
+
+    /// Materializes the current template into profiles created under an older version,
+    /// keeping their existing values, and returns the number of profiles affected.
+    pub async fn migrate_template(
+        instance: &ProfileInstance,
+        dry_run: bool,
+    ) -> Result<u64, mongodb::error::Error> {
+        Self::migrate_template_int(instance, dry_run).await
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader},
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    extract::connect_info::Connected,
+    serve::{IncomingStream, Listener},
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_rustls::{
+    rustls::{
+        crypto::ring, pki_types::CertificateDer, server::WebPkiClientVerifier, RootCertStore,
+        ServerConfig,
+    },
+    server::TlsStream,
+    TlsAcceptor,
+};
+use tracing::{debug, error, info, warn};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::{ServerTlsConfig, StartupError};
+
+/// Time a client has to complete the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections whose handshake completed but weren't accepted yet.
+const BACKLOG: usize = 128;
+
+/// Remote end of a TLS connection, available to handlers as `ConnectInfo<TlsPeer>`.
+#[derive(Clone, Debug)]
+pub struct TlsPeer {
+    pub addr: SocketAddr,
+    /// Service the client certificate maps to through `server.tls.identities`.
+    pub identity: Option<String>,
+}
+
+/// Listener terminating TLS, with handshakes running in their own tasks so a slow client
+/// can't hold up others, and certificates reloaded when their files change.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, TlsPeer)>,
+}
+
+impl TlsListener {
+    pub async fn bind(address: SocketAddr, config: &ServerTlsConfig) -> Result<Self, StartupError> {
+        let acceptor = Arc::new(RwLock::new(
+            Self::acceptor(config).map_err(StartupError::Tls)?,
+        ));
+        let tcp = TcpListener::bind(address)
+            .await
+            .map_err(|source| StartupError::Bind {
+                address: address.to_string(),
+                source,
+            })?;
+        let local_addr = tcp.local_addr().map_err(|source| StartupError::Bind {
+            address: address.to_string(),
+            source,
+        })?;
+
+        let (sender, incoming) = mpsc::channel(BACKLOG);
+        tokio::spawn(Self::accept_loop(
+            tcp,
+            acceptor.clone(),
+            Arc::new(
+                config
+                    .identities
+                    .iter()
+                    .map(|(name, service)| (Self::canonical_name(name), service.clone()))
+                    .collect(),
+            ),
+            sender,
+        ));
+        tokio::spawn(Self::reload_loop(acceptor, config.clone()));
+
+        Ok(Self {
+            local_addr,
+            incoming,
+        })
+    }
+
+    fn acceptor(config: &ServerTlsConfig) -> Result<TlsAcceptor, String> {
+        let certs = Self::certs(&config.cert)?;
+        let key = rustls_pemfile::private_key(&mut BufReader::new(
+            File::open(&config.key).map_err(|e| format!("{}: {e}", config.key.display()))?,
+        ))
+        .map_err(|e| format!("{}: {e}", config.key.display()))?
+        .ok_or_else(|| format!("{}: no private key found", config.key.display()))?;
+
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?;
+
+        let builder = match &config.client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+
+                for cert in Self::certs(client_ca)? {
+                    roots
+                        .add(cert)
+                        .map_err(|e| format!("{}: {e}", client_ca.display()))?;
+                }
+
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                let verifier = if config.client_required {
+                    verifier
+                } else {
+                    verifier.allow_unauthenticated()
+                };
+
+                builder.with_client_cert_verifier(verifier.build().map_err(|e| e.to_string())?)
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut server = builder
+            .with_single_cert(certs, key)
+            .map_err(|e| e.to_string())?;
+        server.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(server)))
+    }
+
+    fn certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        rustls_pemfile::certs(&mut BufReader::new(file))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Maps the client certificate to a service, by its full subject or its common name.
+    fn identity(
+        stream: &TlsStream<TcpStream>,
+        identities: &BTreeMap<String, String>,
+    ) -> Option<String> {
+        Self::identity_of(stream.get_ref().1.peer_certificates()?.first()?, identities)
+    }
+
+    /// Canonical form of a distinguished name, with attributes sorted, types upper cased and
+    /// spaces around separators dropped, so `CN=orders, O=Example` matches `o=Example,CN=orders`.
+    /// Escaped separators aren't supported.
+    fn canonical_name(name: &str) -> String {
+        let mut attributes = name
+            .split([',', '+'])
+            .map(|attribute| match attribute.split_once('=') {
+                Some((kind, value)) => format!("{}={}", kind.trim().to_uppercase(), value.trim()),
+                None => attribute.trim().to_string(),
+            })
+            .collect::<Vec<_>>();
+        attributes.sort();
+        attributes.join(",")
+    }
+
+    /// Looks up a certificate in identities keyed by [`TlsListener::canonical_name`].
+    fn identity_of(der: &[u8], identities: &BTreeMap<String, String>) -> Option<String> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+        let subject = cert.subject();
+
+        identities
+            .get(&Self::canonical_name(&subject.to_string()))
+            .or_else(|| {
+                subject
+                    .iter_common_name()
+                    .next()
+                    .and_then(|cn| cn.as_str().ok())
+                    .and_then(|cn| identities.get(cn))
+            })
+            .cloned()
+    }
+
+    async fn accept_loop(
+        tcp: TcpListener,
+        acceptor: Arc<RwLock<TlsAcceptor>>,
+        identities: Arc<BTreeMap<String, String>>,
+        sender: mpsc::Sender<(TlsStream<TcpStream>, TlsPeer)>,
+    ) {
+        while !sender.is_closed() {
+            let (stream, addr) = match tcp.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // Mostly running out of file descriptors, give connections time to close.
+                    warn!(error = %e, "failed to accept connection");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            let acceptor = acceptor.read().unwrap().clone();
+            let identities = identities.clone();
+            let sender = sender.clone();
+
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let identity = Self::identity(&stream, &identities);
+                        let _ = sender.send((stream, TlsPeer { addr, identity })).await;
+                    }
+                    Ok(Err(e)) => debug!(%addr, error = %e, "TLS handshake failed"),
+                    Err(_) => debug!(%addr, "TLS handshake timed out"),
+                }
+            });
+        }
+    }
+
+    async fn reload_loop(acceptor: Arc<RwLock<TlsAcceptor>>, config: ServerTlsConfig) {
+        let modified = |config: &ServerTlsConfig| {
+            [
+                Some(&config.cert),
+                Some(&config.key),
+                config.client_ca.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+            .collect::<Vec<Option<SystemTime>>>()
+        };
+
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(config.reload_interval.max(1)));
+        let mut last = modified(&config);
+
+        loop {
+            interval.tick().await;
+            let current = modified(&config);
+
+            if current == last {
+                continue;
+            }
+
+            last = current;
+
+            match Self::acceptor(&config) {
+                Ok(reloaded) => {
+                    *acceptor.write().unwrap() = reloaded;
+                    info!("reloaded TLS certificates");
+                }
+                Err(e) => error!(error = %e, "failed to reload TLS certificates"),
+            }
+        }
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = TlsPeer;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(accepted) => accepted,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(TlsPeer {
+            addr: self.local_addr,
+            identity: None,
+        })
+    }
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for TlsPeer {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        stream.remote_addr().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-signed, subject `C=US, O=Example, CN=orders`.
+    const CLIENT_CERT: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBtzCCAV2gAwIBAgIUMkoJJHPoGoQ176EoHbyLjqBBr2gwCgYIKoZIzj0EAwIw\n\
+MDELMAkGA1UEBhMCVVMxEDAOBgNVBAoMB0V4YW1wbGUxDzANBgNVBAMMBm9yZGVy\n\
+czAgFw0yNjEwMTkwNDIwMzVaGA8yMTI2MDkyNTA0MjAzNVowMDELMAkGA1UEBhMC\n\
+VVMxEDAOBgNVBAoMB0V4YW1wbGUxDzANBgNVBAMMBm9yZGVyczBZMBMGByqGSM49\n\
+AgEGCCqGSM49AwEHA0IABCVxJIAGyRU6NMG7B5/qKieLbGd4EGNTOop25UV+IzFt\n\
+ZC+ffMCdomkF5gPiKdw5+pPGs/kmwRx7892zxhh0lLSjUzBRMB0GA1UdDgQWBBQ2\n\
+y3qo3vtA+Nph5jBVnvtXrlILizAfBgNVHSMEGDAWgBQ2y3qo3vtA+Nph5jBVnvtX\n\
+rlILizAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIQCTyyKDpXQd\n\
+B80vZchWOUgP2+9JGkSeSp+29urMeDMdygIgYopWji7TrgcWeA/7L2jHSfoJi+Qf\n\
+4KDdZGvNsrheR6A=\n\
+-----END CERTIFICATE-----";
+
+    fn identities(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(name, service)| (TlsListener::canonical_name(name), service.to_string()))
+            .collect()
+    }
+
+    fn identity(entries: &[(&str, &str)]) -> Option<String> {
+        let der = rustls_pemfile::certs(&mut CLIENT_CERT.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+        TlsListener::identity_of(&der, &identities(entries))
+    }
+
+    #[test]
+    fn identity_by_subject_in_any_order() {
+        for subject in [
+            "C=US, O=Example, CN=orders",
+            "CN=orders,O=Example,C=US",
+            "cn=orders, o=Example ,c=US",
+        ] {
+            assert_eq!(
+                identity(&[(subject, "orders")]).as_deref(),
+                Some("orders"),
+                "{subject}"
+            );
+        }
+    }
+
+    #[test]
+    fn identity_by_common_name() {
+        assert_eq!(
+            identity(&[("orders", "billing")]).as_deref(),
+            Some("billing")
+        );
+    }
+
+    #[test]
+    fn identity_needs_the_whole_subject() {
+        assert_eq!(identity(&[("CN=orders,O=Example", "orders")]), None);
+        assert_eq!(identity(&[("C=US,O=Other,CN=orders", "orders")]), None);
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::Instant;
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{info_span, Instrument};
+use tracing_subscriber::EnvFilter;
+
+use crate::{LogConfig, LogFormat};
+
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+pub const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static TRACE_CONTEXT: TraceContext;
+}
+
+/// W3C trace context of the request being handled.
+#[derive(Clone)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub parent_id: u64,
+    pub flags: u8,
+    pub state: Option<String>,
+}
+
+impl TraceContext {
+    /// Starts a new sampled trace.
+    pub fn root() -> Self {
+        Self {
+            trace_id: rand::random::<u128>().max(1),
+            parent_id: rand::random::<u64>().max(1),
+            flags: 1,
+            state: None,
+        }
+    }
+
+    /// Parses a version 00 `traceparent` header, rejecting all-zero ids.
+    pub fn parse(traceparent: &str, state: Option<String>) -> Option<Self> {
+        let mut parts = traceparent.split('-');
+        let (version, trace_id, parent_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+
+        if version != "00" || trace_id.len() != 32 || parent_id.len() != 16 || flags.len() != 2 {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: u128::from_str_radix(trace_id, 16)
+                .ok()
+                .filter(|id| *id != 0)?,
+            parent_id: u64::from_str_radix(parent_id, 16)
+                .ok()
+                .filter(|id| *id != 0)?,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+            state,
+        })
+    }
+
+    /// Context of the current task, if it handles a request.
+    pub fn current() -> Option<Self> {
+        TRACE_CONTEXT.try_with(Clone::clone).ok()
+    }
+
+    /// `traceparent` for an outgoing call made on behalf of this context.
+    pub fn child(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id,
+            rand::random::<u64>().max(1),
+            self.flags
+        )
+    }
+}
+
+/// Middleware running each request in a span carrying its request id, with the trace context
+/// available to outgoing calls.
+pub(crate) async fn trace(request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let context = headers
+        .get(TRACEPARENT)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|traceparent| {
+            TraceContext::parse(
+                traceparent,
+                headers
+                    .get(TRACESTATE)
+                    .and_then(|header| header.to_str().ok())
+                    .map(str::to_string),
+            )
+        })
+        .unwrap_or_else(TraceContext::root);
+    let request_id = headers
+        .get(X_REQUEST_ID)
+        .and_then(|header| header.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:032x}", context.trace_id));
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        trace_id = %format!("{:032x}", context.trace_id),
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let start = Instant::now();
+
+    let mut response = TRACE_CONTEXT
+        .scope(context, next.run(request))
+        .instrument(span.clone())
+        .await;
+
+    span.in_scope(|| {
+        let status = response.status().as_u16();
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+
+        if response.status().is_server_error() {
+            tracing::warn!(status, elapsed_ms, "request failed");
+        } else {
+            tracing::info!(status, elapsed_ms, "request handled");
+        }
+    });
+
+    if let Ok(request_id) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(X_REQUEST_ID, request_id);
+    }
+
+    response
+}
+
+/// Installs the global log subscriber, filtered by `RUST_LOG` and `info` by default.
+pub fn init_logging(config: &LogConfig) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match config.format {
+        LogFormat::Json => builder.json().init(),
+        LogFormat::Text => builder.init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parse_valid() {
+        let context = TraceContext::parse(VALID, Some("congo=t61rcWkgMzE".to_string())).unwrap();
+
+        assert_eq!(context.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(context.parent_id, 0x00f067aa0ba902b7);
+        assert_eq!(context.flags, 1);
+        assert_eq!(context.state.as_deref(), Some("congo=t61rcWkgMzE"));
+    }
+
+    #[test]
+    fn parse_rejects_malformed() {
+        for traceparent in [
+            "",
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-1",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e473g-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-zz",
+        ] {
+            assert!(
+                TraceContext::parse(traceparent, None).is_none(),
+                "{traceparent}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rejects_zero_ids() {
+        assert!(TraceContext::parse(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        assert!(TraceContext::parse(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            None
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn child_keeps_trace() {
+        let context = TraceContext::parse(VALID, None).unwrap();
+        let child = TraceContext::parse(&context.child(), None).unwrap();
+
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_eq!(child.flags, context.flags);
+    }
+}
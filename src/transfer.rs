@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use serde::Serialize;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{instance::ProfileInstance, schema::ProfileExport, Profile, SchemaRegistry};
+
+/// Selects which parts of each profile take part in a bulk transfer.
+#[derive(Clone)]
+pub struct TransferFilter {
+    /// Only transfer these service namespaces, all of them if `None`.
+    pub services: Option<BTreeSet<String>>,
+    pub bucket: bool,
+}
+
+impl Default for TransferFilter {
+    fn default() -> Self {
+        Self {
+            services: None,
+            bucket: true,
+        }
+    }
+}
+
+impl TransferFilter {
+    fn apply(&self, mut profile: ProfileExport) -> ProfileExport {
+        if !self.bucket {
+            profile.bucket.clear();
+        }
+
+        if let Some(services) = &self.services {
+            profile.services.retain(|name, _| services.contains(name));
+        }
+
+        profile
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Set imported keys, leaving other keys of existing profiles untouched.
+    Merge,
+    /// Overwrite the bucket and every imported service namespace as a whole.
+    Replace,
+}
+
+#[derive(Serialize, Default)]
+pub struct ImportReport {
+    pub created: u64,
+    pub updated: u64,
+    /// Profiles that are currently removed and were left alone.
+    pub skipped: u64,
+    /// Schema violations of imported values, when validation only warns.
+    pub warnings: Vec<String>,
+}
+
+macro_rules! io_fail {
+    ($x: expr) => {
+        $x.map_err(|e| mongodb::error::Error::custom(e.to_string()))?
+    };
+}
+
+impl Profile {
+    /// Writes every profile as one line of JSON, returning the number of profiles written.
+    pub async fn export_all(
+        instance: &ProfileInstance,
+        filter: &TransferFilter,
+        mut out: impl AsyncWrite + Unpin,
+    ) -> Result<u64, mongodb::error::Error> {
+        let mut cursor = instance
+            .profiles
+            .find(doc! { "meta.deleted": Bson::Null })
+            .await?;
+        let mut count = 0;
+
+        while let Some(profile) = cursor.try_next().await? {
+            let mut line = io_fail!(serde_json::to_vec(&filter.apply(profile.into_export())));
+            line.push(b'\n');
+            io_fail!(out.write_all(&line).await);
+            count += 1;
+        }
+
+        io_fail!(out.flush().await);
+        Ok(count)
+    }
+
+    /// Reads profiles written by [`Profile::export_all`], one per line.
+    pub async fn import_all(
+        instance: &ProfileInstance,
+        filter: &TransferFilter,
+        mode: ImportMode,
+        dry_run: bool,
+        input: impl AsyncBufRead + Unpin,
+    ) -> Result<ImportReport, mongodb::error::Error> {
+        let mut report = ImportReport::default();
+        let mut lines = input.lines();
+        let mut line_no = 0;
+
+        while let Some(line) = io_fail!(lines.next_line().await) {
+            line_no += 1;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let at_line = |e: &dyn std::fmt::Display| {
+                mongodb::error::Error::custom(format!("line {line_no}: {e}"))
+            };
+            let profile: ProfileExport = serde_json::from_str(&line).map_err(|e| at_line(&e))?;
+            let profile = filter.apply(profile);
+            report.warnings.extend(
+                Self::validate_import(instance, &profile)
+                    .await
+                    .map_err(|e| {
+                        at_line(
+                            &e.get_custom::<String>()
+                                .cloned()
+                                .unwrap_or(e.kind.to_string()),
+                        )
+                    })?
+                    .into_iter()
+                    .map(|warning| format!("line {line_no}: {warning}")),
+            );
+
+            if dry_run {
+                let exists = |filter| async {
+                    Ok::<_, mongodb::error::Error>(
+                        instance
+                            .profiles_doc
+                            .count_documents(filter)
+                            .limit(1)
+                            .await?
+                            != 0,
+                    )
+                };
+
+                if exists(Self::filter(profile.id)).await? {
+                    report.updated += 1;
+                } else if exists(doc! { "_id": Bson::Int64(profile.id as i64) }).await? {
+                    report.skipped += 1;
+                } else {
+                    report.created += 1;
+                }
+
+                continue;
+            }
+
+            match instance
+                .profiles
+                .update_one(
+                    Self::filter(profile.id),
+                    Self::import_update(&profile, filter, mode),
+                )
+                .upsert(true)
+                .await
+                .inspect(|_| instance.cache.invalidate(profile.id))
+            {
+                Ok(res) if res.upserted_id.is_some() => report.created += 1,
+                Ok(_) => report.updated += 1,
+                Err(e) if Self::is_duplicate(&e) => report.skipped += 1,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Checks imported values against the schema registry like regular writes, returning the
+    /// violations when validation only warns.
+    async fn validate_import(
+        instance: &ProfileInstance,
+        profile: &ProfileExport,
+    ) -> Result<Vec<String>, mongodb::error::Error> {
+        let entries = |entries: &BTreeMap<String, String>| {
+            entries
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        let mut warnings =
+            SchemaRegistry::validate(instance, None, &entries(&profile.bucket)).await?;
+
+        for (service, values) in profile.services.iter() {
+            Self::check_service(instance, service).await?;
+            warnings
+                .extend(SchemaRegistry::validate(instance, Some(service), &entries(values)).await?);
+        }
+
+        Ok(warnings)
+    }
+
+    fn import_update(
+        profile: &ProfileExport,
+        filter: &TransferFilter,
+        mode: ImportMode,
+    ) -> Document {
+        let encode = |entries: &BTreeMap<String, String>| {
+            entries
+                .iter()
+                .map(|(k, v)| (Self::encode(k), Bson::String(v.clone())))
+                .collect::<Document>()
+        };
+
+        let mut m_set = doc! {
+            "meta.modified": Bson::Int64(Self::now() as i64),
+            "meta.modified_by": profile.meta.modified_by.as_deref(),
+        };
+
+        match mode {
+            ImportMode::Merge => {
+                for (k, v) in profile.bucket.iter() {
+                    m_set.insert(format!("bucket.{}", Self::encode(k)), v.clone());
+                }
+
+                for (service, entries) in profile.services.iter() {
+                    for (k, v) in entries.iter() {
+                        m_set.insert(format!("services.{service}.{}", Self::encode(k)), v.clone());
+                    }
+                }
+            }
+            ImportMode::Replace => {
+                if filter.bucket {
+                    m_set.insert("bucket", encode(&profile.bucket));
+                }
+
+                for (service, entries) in profile.services.iter() {
+                    m_set.insert(format!("services.{service}"), encode(entries));
+                }
+            }
+        }
+
+        let created = if profile.meta.created == 0 {
+            Self::now()
+        } else {
+            profile.meta.created
+        };
+
+        doc! {
+            "$set": m_set,
+            "$setOnInsert": { "meta.created": Bson::Int64(created as i64) },
+        }
+    }
+}